@@ -10,7 +10,7 @@ use tracing::{error, info};
 
 use egui_async::{Bind, EguiAsyncPlugin};
 use egui_file_dialog::FileDialog;
-use emergence_zk::{Kasten, KastenHandle, ZettelId, ZkError};
+use emergence_zk::{Kasten, KastenHandle, SearchHit, Severity, ZettelId, ZkError};
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 pub struct EmergenceApp {
@@ -23,6 +23,9 @@ pub struct EmergenceApp {
     kasten_bind: Bind<KastenHandle, ZkError>,
     kasten_sender: tokio::sync::mpsc::Sender<KastenHandle>,
 
+    search_query: String,
+    search_bind: Bind<Vec<SearchHit>, ZkError>,
+
     curr_kasten_id: Option<ZettelId>,
 }
 
@@ -52,6 +55,8 @@ impl EmergenceApp {
             kasten_bind: Bind::default(),
             curr_kasten_id: None,
             kasten_sender: tx,
+            search_query: String::new(),
+            search_bind: Bind::default(),
         }
     }
 
@@ -98,17 +103,73 @@ impl eframe::App for EmergenceApp {
             .show(ctx, |ui| {
                 match self.kasten_bind.state() {
                     Finished(k_handle) => {
-                        let k = k_handle.lock().expect("must not be poisoned");
+                        let mut k = k_handle.lock().expect("must not be poisoned");
+
+                        let search = ui.add(
+                            egui::TextEdit::singleline(&mut self.search_query).hint_text("Search"),
+                        );
+                        if search.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            let ws = k.ws.clone();
+                            let query = self.search_query.clone();
+                            self.search_bind
+                                .request(async move { SearchHit::query(&query, &ws).await });
+                        }
+
+                        if !k.diagnostics.is_empty() {
+                            egui::CollapsingHeader::new(format!(
+                                "Problems ({})",
+                                k.diagnostics.len()
+                            ))
+                            .show(ui, |ui| {
+                                for diagnostic in &k.diagnostics {
+                                    let color = match diagnostic.severity {
+                                        Severity::Error => Color32::RED,
+                                        Severity::Warning => Color32::YELLOW,
+                                    };
+                                    ui.colored_label(color, diagnostic.to_string());
+                                }
+                            });
+                        }
+
+                        if let Some(Ok(hits)) = self.search_bind.read_as_ref() {
+                            for hit in hits {
+                                if ui.link(&hit.title).clicked() {
+                                    k.most_recently_edited = k.zid_to_gid.get(&hit.id).copied();
+                                }
+                                ui.label(&hit.snippet);
+                            }
+                            ui.separator();
+                        }
+
                         if let Some(recently_edited) = k.most_recently_edited {
                             let zettel =
                                 k.graph.node(recently_edited).expect("must exist").payload();
 
                             ui.vertical_centered(|ui| {
                                 ui.heading(zettel.front_matter.title.clone());
+
+                                if ui
+                                    .button("Edit")
+                                    .on_hover_text("Open in the editor of this ZettelKasten")
+                                    .clicked()
+                                    && let Err(e) = k.ws.config.editor_command(&zettel.path).spawn()
+                                {
+                                    error!("Unable to open editor: {e:#?}");
+                                }
                             });
                             egui::ScrollArea::vertical().show(ui, |ui| {
                                 let mut cache = CommonMarkCache::default();
                                 CommonMarkViewer::new().show(ui, &mut cache, &zettel.content);
+
+                                let backlinks = k.backlinks(&zettel.id);
+                                if !backlinks.is_empty() {
+                                    ui.separator();
+                                    ui.heading("Backlinks");
+                                    for backlink in backlinks {
+                                        ui.label(RichText::new(&backlink.source_title).strong());
+                                        ui.label(&backlink.context);
+                                    }
+                                }
                             });
                         } else {
                             egui::ScrollArea::vertical().show(ui, |_| {});
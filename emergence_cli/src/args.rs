@@ -1,4 +1,6 @@
-use clap::{Args, Parser, Subcommand, arg};
+use std::ops::RangeInclusive;
+
+use clap::{Args, Parser, Subcommand};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -18,6 +20,54 @@ pub enum Commands {
     /// Lists Zettels
     List,
 
+    /// Lists the Zettels linking to a Zettel
+    Backlinks(BacklinksArgs),
+
+    /// Lists links that don't point at any Zettel
+    BrokenLinks,
+
+    /// Lists links that point outside of the `ZettelKasten`
+    ExternalLinks,
+
+    /// Searches the title and content of every Zettel
+    Search(SearchArgs),
+
+    /// Lists Zettels that couldn't be parsed and other problems inside the `ZettelKasten`
+    Check,
+
+    /// Lists Zettels whose properties match a filter like `status = draft` or `source set`
+    Filter(FilterArgs),
+
+    /// Lists Zettels matching a query like `tag:rust -tag:draft created>2025-01-01 "borrow checker"`
+    Query(QueryArgs),
+
+    /// Changes the title of a Zettel and rewrites the links referring to it by its old title
+    Rename(RenameArgs),
+
+    /// Moves a Zettel into the trash and lists the Zettels whose links to it are broken now
+    Delete(DeleteArgs),
+
+    /// Moves a Zettel out of the trash, back to where it was deleted from
+    Restore(RestoreArgs),
+
+    /// Merges a Zettel into another one, pointing every link at the one that is kept
+    Merge(MergeArgs),
+
+    /// Splits headings or line ranges out of a Zettel into new Zettels
+    Split(SplitArgs),
+
+    /// Lists the tags of the `ZettelKasten` as a tree, with how many Zettels use each of them
+    Tags,
+
+    /// Lists Zettels with a tag, including tags nested below it like `project/emergence`
+    Tagged(TaggedArgs),
+
+    /// Renames a tag in every Zettel, merging it into the new one if that exists already
+    RenameTag(RenameTagArgs),
+
+    /// Reports orphaned Zettels, dead ends, hubs, bridges and clusters of the `ZettelKasten`
+    Analyze(AnalyzeArgs),
+
     Test, // #[command(subcommand)]
           // Group(GroupSubcommand),
 
@@ -45,6 +95,107 @@ pub struct NewArgs {
     pub tags: Vec<String>,
 }
 
+#[derive(Debug, Args)]
+pub struct BacklinksArgs {
+    /// Id of the Zettel to list the backlinks of
+    pub id: String,
+}
+
+#[derive(Debug, Args)]
+pub struct SearchArgs {
+    /// Words that every matching Zettel has to contain
+    #[arg(required = true)]
+    pub query: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct FilterArgs {
+    /// The filter, like `status = draft` or `source set`
+    #[arg(required = true)]
+    pub filter: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct QueryArgs {
+    /// The query, combining terms like `tag:`, `links-to:`, `created>` and words with `AND`, `OR`
+    /// and `NOT`
+    #[arg(required = true)]
+    pub query: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct RenameArgs {
+    /// Id of the Zettel to rename
+    pub id: String,
+    /// The new title
+    #[arg(required = true)]
+    pub title: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct DeleteArgs {
+    /// Id of the Zettel to delete
+    pub id: String,
+}
+
+#[derive(Debug, Args)]
+pub struct RestoreArgs {
+    /// Id of the deleted Zettel to restore
+    pub id: String,
+}
+
+#[derive(Debug, Args)]
+pub struct MergeArgs {
+    /// Id of the Zettel to keep
+    pub keep: String,
+    /// Id of the Zettel whose content and tags are moved into the kept one, it gets deleted
+    pub absorb: String,
+}
+
+#[derive(Debug, Args)]
+pub struct SplitArgs {
+    /// Id of the Zettel to split
+    pub id: String,
+    /// Headings to split off, along with everything below them
+    pub headings: Vec<String>,
+    /// Line ranges of the file to split off, like `8-10`
+    #[arg(short, long, value_parser = parse_line_range)]
+    pub lines: Vec<RangeInclusive<usize>>,
+}
+
+#[derive(Debug, Args)]
+pub struct TaggedArgs {
+    /// The tag, like `project` or `project/emergence`
+    pub tag: String,
+}
+
+#[derive(Debug, Args)]
+pub struct RenameTagArgs {
+    /// The tag to rename, tags nested below it are renamed along with it
+    pub old: String,
+    /// The new name of the tag
+    pub new: String,
+}
+
+#[derive(Debug, Args)]
+pub struct AnalyzeArgs {
+    /// How many Zettels to list for hubs and bridges
+    #[arg(short, long, default_value = "10")]
+    pub top: usize,
+}
+
+fn parse_line_range(arg: &str) -> Result<RangeInclusive<usize>, String> {
+    let (start, end) = arg
+        .split_once('-')
+        .ok_or_else(|| format!("expected a range like 3-10, got {arg:?}"))?;
+    let parse = |line: &str| {
+        line.trim()
+            .parse::<usize>()
+            .map_err(|e| format!("{line:?} isn't a line: {e}"))
+    };
+    Ok(parse(start)?..=parse(end)?)
+}
+
 // #[derive(Subcommand, Debug)]
 // /// Subcommand to manage tars groups.
 // pub enum GroupSubcommand {
@@ -3,20 +3,21 @@ use emergence_zk::{
     entities::{tag, zettel, zettel_tag},
     entity::{ActiveModelTrait as _, EntityTrait as _},
 };
-use std::{
-    env::{self, current_dir},
-    process::Command,
-};
+use std::env::current_dir;
 
 use clap::Parser as _;
 use color_eyre::{eyre::Result, owo_colors::OwoColorize as _};
 use emergence_zk::{
-    EmergenceDb, Kasten, Tag, Zettel, ZettelBuilder,
+    Color, EmergenceDb, Kasten, PropertyFilter, Severity, Split, Tag, TagNode, Zettel,
+    ZettelBuilder,
     entities::{self},
     entity::ActiveValue,
 };
 
-use crate::args::{CliArgs, Commands};
+use crate::args::{
+    AnalyzeArgs, BacklinksArgs, CliArgs, Commands, DeleteArgs, FilterArgs, MergeArgs, QueryArgs,
+    RenameArgs, RenameTagArgs, RestoreArgs, SearchArgs, SplitArgs, TaggedArgs,
+};
 
 mod args;
 
@@ -60,16 +61,12 @@ async fn main() -> Result<()> {
             }
 
             for tag in args.tags {
-                zb.add_tag(Tag::new(tag, "penis", &ws).await?);
+                zb.add_tag(Tag::get_or_new(tag, &ws).await?);
             }
 
             let z: Zettel = zb.build().await?;
 
-            let editor = env::var("EDITOR")
-                .or_else(|_| env::var("VISUAL"))
-                .unwrap_or_else(|_| "vim".to_owned());
-
-            Command::new(editor).arg(&z.path).status()?;
+            ws.config.editor_command(&z.path).status()?;
 
             Ok(())
         }
@@ -87,6 +84,38 @@ async fn main() -> Result<()> {
             Ok(())
         }
 
+        Commands::Backlinks(args) => backlinks(args).await,
+
+        Commands::BrokenLinks => broken_links().await,
+
+        Commands::ExternalLinks => external_links().await,
+
+        Commands::Search(args) => search(args).await,
+
+        Commands::Check => check().await,
+
+        Commands::Filter(args) => filter(args).await,
+
+        Commands::Query(args) => query(args).await,
+
+        Commands::Rename(args) => rename(args).await,
+
+        Commands::Delete(args) => delete(args).await,
+
+        Commands::Restore(args) => restore(args).await,
+
+        Commands::Merge(args) => merge(args).await,
+
+        Commands::Split(args) => split(args).await,
+
+        Commands::Tags => tags().await,
+
+        Commands::Tagged(args) => tagged(args).await,
+
+        Commands::RenameTag(args) => rename_tag(args).await,
+
+        Commands::Analyze(args) => analyze(args).await,
+
         Commands::Test => {
             let pwd = current_dir()?;
             let db = EmergenceDb::connect(pwd).await?;
@@ -107,7 +136,7 @@ async fn main() -> Result<()> {
             let new_tag = tag::ActiveModel {
                 name: ActiveValue::Set("penis_tag".to_owned()),
                 nanoid: ActiveValue::Set(ZettelId::default().to_string()),
-                color: ActiveValue::Set("dumb".to_owned()),
+                color: ActiveValue::Set(Color::from_palette("penis_tag").to_string()),
                 ..Default::default()
             };
 
@@ -119,6 +148,7 @@ async fn main() -> Result<()> {
             let tag_zettel_link = zettel_tag::ActiveModel {
                 tag_nano_id: ActiveValue::Set(tag.nanoid),
                 zettel_nano_id: ActiveValue::Set(zettel.nanoid),
+                inline: ActiveValue::Set(false),
             };
 
             let x = tag_zettel_link
@@ -140,3 +170,336 @@ async fn main() -> Result<()> {
         }
     }
 }
+
+async fn backlinks(args: BacklinksArgs) -> Result<()> {
+    let pwd = current_dir()?;
+    let kasten = Kasten::parse(pwd).await?;
+
+    for backlink in kasten.backlinks(&ZettelId::from(args.id.as_str())) {
+        println!(
+            "{} ({}:{})\n    {}",
+            backlink.source_title.green(),
+            backlink.source,
+            backlink.line,
+            backlink.context
+        );
+    }
+
+    Ok(())
+}
+
+async fn broken_links() -> Result<()> {
+    let pwd = current_dir()?;
+    let kasten = Kasten::parse(pwd).await?;
+
+    for link in kasten.dangling_links() {
+        println!(
+            "{}:{} -> {} ({})",
+            link.source,
+            link.line,
+            link.target.red(),
+            link.reason
+        );
+    }
+
+    Ok(())
+}
+
+async fn external_links() -> Result<()> {
+    let pwd = current_dir()?;
+    let kasten = Kasten::parse(pwd).await?;
+
+    for link in kasten.external_links() {
+        println!("{}:{} -> {}", link.source, link.line, link.url.blue());
+    }
+
+    Ok(())
+}
+
+async fn search(args: SearchArgs) -> Result<()> {
+    let pwd = current_dir()?;
+    let kasten = Kasten::parse(pwd).await?;
+
+    for hit in kasten.search(&args.query.join(" ")).await? {
+        println!("{} ({})\n    {}", hit.title.green(), hit.id, hit.snippet);
+    }
+
+    Ok(())
+}
+
+async fn check() -> Result<()> {
+    let pwd = current_dir()?;
+    let kasten = Kasten::parse(pwd).await?;
+
+    for diagnostic in &kasten.diagnostics {
+        let severity = match diagnostic.severity {
+            Severity::Error => diagnostic.severity.red().to_string(),
+            Severity::Warning => diagnostic.severity.yellow().to_string(),
+        };
+
+        println!(
+            "{}:{}:{}: {severity}: {}",
+            diagnostic.path.display(),
+            diagnostic.line,
+            diagnostic.column,
+            diagnostic.message
+        );
+    }
+
+    Ok(())
+}
+
+async fn filter(args: FilterArgs) -> Result<()> {
+    let filter: PropertyFilter = args.filter.join(" ").parse()?;
+
+    let pwd = current_dir()?;
+    let kasten = Kasten::parse(pwd).await?;
+
+    for id in kasten.filter(&filter).await? {
+        let Some(zettel) = kasten
+            .zid_to_gid
+            .get(&id)
+            .and_then(|gid| kasten.graph.node(*gid))
+            .map(|node| node.payload())
+        else {
+            continue;
+        };
+
+        println!("{} ({})", zettel.front_matter.title.green(), id);
+    }
+
+    Ok(())
+}
+
+async fn query(args: QueryArgs) -> Result<()> {
+    let pwd = current_dir()?;
+    let kasten = Kasten::parse(pwd).await?;
+
+    for id in kasten.query(&args.query.join(" ")).await? {
+        let Some(zettel) = kasten.zettel(&id) else {
+            continue;
+        };
+
+        println!("{} ({})", zettel.front_matter.title.green(), id);
+    }
+
+    Ok(())
+}
+
+async fn rename(args: RenameArgs) -> Result<()> {
+    let pwd = current_dir()?;
+    let mut kasten = Kasten::parse(&pwd).await?;
+
+    let touched = kasten
+        .rename_zettel(&ZettelId::from(args.id.as_str()), &args.title.join(" "))
+        .await?;
+
+    for path in touched {
+        println!(
+            "{}",
+            path.strip_prefix(&pwd).unwrap_or(&path).display().green()
+        );
+    }
+
+    Ok(())
+}
+
+async fn delete(args: DeleteArgs) -> Result<()> {
+    let pwd = current_dir()?;
+    let mut kasten = Kasten::parse(&pwd).await?;
+
+    let broken = kasten
+        .delete_zettel(&ZettelId::from(args.id.as_str()))
+        .await?;
+
+    println!("Moved {} into the trash", args.id.green());
+    if !broken.is_empty() {
+        println!("These Zettels link to it:");
+    }
+    for id in broken {
+        let title = kasten
+            .zettel(&id)
+            .map(|zettel| zettel.front_matter.title.as_str())
+            .unwrap_or_default();
+        println!("    {} ({})", title.red(), id);
+    }
+
+    Ok(())
+}
+
+async fn restore(args: RestoreArgs) -> Result<()> {
+    let pwd = current_dir()?;
+    let mut kasten = Kasten::parse(&pwd).await?;
+
+    let repaired = kasten
+        .restore_zettel(&ZettelId::from(args.id.as_str()))
+        .await?;
+
+    println!("Restored {}", args.id.green());
+    for id in repaired {
+        let title = kasten
+            .zettel(&id)
+            .map(|zettel| zettel.front_matter.title.as_str())
+            .unwrap_or_default();
+        println!("    {} ({}) links to it again", title.green(), id);
+    }
+
+    Ok(())
+}
+
+async fn merge(args: MergeArgs) -> Result<()> {
+    let pwd = current_dir()?;
+    let mut kasten = Kasten::parse(&pwd).await?;
+
+    let touched = kasten
+        .merge_zettels(
+            &ZettelId::from(args.keep.as_str()),
+            &ZettelId::from(args.absorb.as_str()),
+        )
+        .await?;
+
+    println!("Merged {} into {}", args.absorb.red(), args.keep.green());
+    for path in touched {
+        println!(
+            "{}",
+            path.strip_prefix(&pwd).unwrap_or(&path).display().green()
+        );
+    }
+
+    Ok(())
+}
+
+async fn split(args: SplitArgs) -> Result<()> {
+    let pwd = current_dir()?;
+    let mut kasten = Kasten::parse(&pwd).await?;
+
+    let splits = args
+        .headings
+        .into_iter()
+        .map(Split::Heading)
+        .chain(args.lines.into_iter().map(Split::Lines))
+        .collect::<Vec<_>>();
+
+    let pieces = kasten
+        .split_zettel(&ZettelId::from(args.id.as_str()), &splits)
+        .await?;
+
+    for id in pieces {
+        let title = kasten
+            .zettel(&id)
+            .map(|zettel| zettel.front_matter.title.as_str())
+            .unwrap_or_default();
+        println!("{} ({})", title.green(), id);
+    }
+
+    Ok(())
+}
+
+async fn tags() -> Result<()> {
+    fn print(nodes: &[TagNode], depth: usize) {
+        for node in nodes {
+            let color = node.color.unwrap_or(Color::rgb(0xff, 0xff, 0xff));
+            println!(
+                "{}{} ({}/{})",
+                "  ".repeat(depth),
+                node.leaf().truecolor(color.r, color.g, color.b),
+                node.count,
+                node.total
+            );
+            print(&node.children, depth + 1);
+        }
+    }
+
+    let pwd = current_dir()?;
+    let kasten = Kasten::parse(pwd).await?;
+
+    print(&kasten.tag_tree().await?, 0);
+
+    Ok(())
+}
+
+async fn tagged(args: TaggedArgs) -> Result<()> {
+    let pwd = current_dir()?;
+    let kasten = Kasten::parse(pwd).await?;
+
+    for id in kasten.tagged(&args.tag).await? {
+        let Some(zettel) = kasten.zettel(&id) else {
+            continue;
+        };
+
+        println!("{} ({})", zettel.front_matter.title.green(), id);
+    }
+
+    Ok(())
+}
+
+async fn rename_tag(args: RenameTagArgs) -> Result<()> {
+    let pwd = current_dir()?;
+    let mut kasten = Kasten::parse(&pwd).await?;
+
+    for path in kasten.rename_tag(&args.old, &args.new).await? {
+        println!(
+            "{}",
+            path.strip_prefix(&pwd).unwrap_or(&path).display().green()
+        );
+    }
+
+    Ok(())
+}
+
+async fn analyze(args: AnalyzeArgs) -> Result<()> {
+    let pwd = current_dir()?;
+    let kasten = Kasten::parse(pwd).await?;
+    let analytics = kasten.analytics();
+
+    let title = |id: &ZettelId| {
+        kasten
+            .zettel(id)
+            .map(|zettel| zettel.front_matter.title.clone())
+            .unwrap_or_else(|| id.to_string())
+    };
+
+    println!("{}", "Orphans".yellow());
+    for note in analytics.orphans() {
+        println!("  {} ({})", title(&note.id).green(), note.id);
+    }
+
+    println!("{}", "Dead ends".yellow());
+    for note in analytics.dead_ends() {
+        println!("  {} ({})", title(&note.id).green(), note.id);
+    }
+
+    println!("{}", "Hubs".yellow());
+    for note in analytics.hubs(args.top) {
+        println!(
+            "  {} ({}) {} backlinks, PageRank {:.3}",
+            title(&note.id).green(),
+            note.id,
+            note.in_degree,
+            note.page_rank
+        );
+    }
+
+    println!("{}", "Bridges".yellow());
+    for note in analytics.bridges(args.top) {
+        println!(
+            "  {} ({}) betweenness {:.3}",
+            title(&note.id).green(),
+            note.id,
+            note.betweenness
+        );
+    }
+
+    for (name, groups) in [
+        ("Components", &analytics.components),
+        ("Communities", &analytics.communities),
+    ] {
+        println!("{} ({})", name.yellow(), groups.len());
+        for group in groups {
+            let titles = group.iter().map(title).collect::<Vec<_>>();
+            println!("  {}: {}", group.len(), titles.join(", "));
+        }
+    }
+
+    Ok(())
+}
@@ -2,7 +2,16 @@ pub use sea_orm_migration::prelude::*;
 
 pub(crate) mod m20251104_023917_create_tag_table;
 pub(crate) mod m20251104_024116_create_zettel_table;
-mod m20251104_050736_create_zettel_tag_table;
+pub(crate) mod m20251104_050736_create_zettel_tag_table;
+pub(crate) mod m20251120_013502_create_link_table;
+mod m20251121_203114_add_relation_to_link;
+mod m20251123_181945_create_zettel_fts_table;
+mod m20251125_224610_add_parse_cache;
+mod m20251127_190342_add_path_to_zettel;
+mod m20251129_153027_create_zettel_property_table;
+mod m20251201_104512_add_parent_to_tag;
+mod m20251203_091744_add_unique_tag_name;
+mod m20251204_162210_add_inline_to_zettel_tag;
 
 pub struct Migrator;
 
@@ -13,6 +22,15 @@ impl MigratorTrait for Migrator {
             Box::new(m20251104_023917_create_tag_table::Migration),
             Box::new(m20251104_024116_create_zettel_table::Migration),
             Box::new(m20251104_050736_create_zettel_tag_table::Migration),
+            Box::new(m20251120_013502_create_link_table::Migration),
+            Box::new(m20251121_203114_add_relation_to_link::Migration),
+            Box::new(m20251123_181945_create_zettel_fts_table::Migration),
+            Box::new(m20251125_224610_add_parse_cache::Migration),
+            Box::new(m20251127_190342_add_path_to_zettel::Migration),
+            Box::new(m20251129_153027_create_zettel_property_table::Migration),
+            Box::new(m20251201_104512_add_parent_to_tag::Migration),
+            Box::new(m20251203_091744_add_unique_tag_name::Migration),
+            Box::new(m20251204_162210_add_inline_to_zettel_tag::Migration),
         ]
     }
 }
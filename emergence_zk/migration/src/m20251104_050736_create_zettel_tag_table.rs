@@ -55,7 +55,7 @@ impl MigrationTrait for Migration {
 }
 
 #[derive(DeriveIden)]
-enum ZettelTag {
+pub enum ZettelTag {
     Table,
     ZettelNanoId,
     TagNanoId,
@@ -0,0 +1,75 @@
+use crate::m20251104_024116_create_zettel_table::Zettel;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Link::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Link::Id).not_null())
+                    .col(string(Link::SourceNanoId).not_null())
+                    // no foreign key here, a link can point at a file the db doesn't know about yet
+                    .col(string(Link::DestNanoId).not_null())
+                    .col(string_null(Link::Anchor))
+                    .col(integer(Link::Line).not_null())
+                    .col(text(Link::Context).not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-link-source_nano_id")
+                            .from(Link::Table, Link::SourceNanoId)
+                            .to(Zettel::Table, Zettel::Nanoid)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-link-source_nano_id")
+                    .table(Link::Table)
+                    .col(Link::SourceNanoId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-link-dest_nano_id")
+                    .table(Link::Table)
+                    .col(Link::DestNanoId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Link::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Link {
+    Table,
+    Id,
+    SourceNanoId,
+    DestNanoId,
+    Anchor,
+    Line,
+    Context,
+}
@@ -0,0 +1,28 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // sea-query has no way of building virtual tables, so we have to write this one by hand
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS zettel_fts USING fts5(nanoid UNINDEXED, title, content)",
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared("DROP TABLE IF EXISTS zettel_fts")
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use crate::m20251104_024116_create_zettel_table::Zettel;
+use crate::m20251120_013502_create_link_table::Link;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // sqlite can only add a single column per `ALTER TABLE`
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Zettel::Table)
+                    .add_column(big_integer_null(ParseCache::Mtime))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Zettel::Table)
+                    .add_column(string_null(ParseCache::ContentHash))
+                    .to_owned(),
+            )
+            .await?;
+
+        // the settings of the kasten the cached zettel was parsed with
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Zettel::Table)
+                    .add_column(string_null(ParseCache::SettingsHash))
+                    .to_owned(),
+            )
+            .await?;
+
+        // lets us cache dangling and external links next to the resolved ones
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Link::Table)
+                    .add_column(string(ParseCache::Kind).default("internal"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Link::Table)
+                    .add_column(text_null(ParseCache::Reason))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            ParseCache::Mtime,
+            ParseCache::ContentHash,
+            ParseCache::SettingsHash,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Zettel::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        for column in [ParseCache::Kind, ParseCache::Reason] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Link::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum ParseCache {
+    Mtime,
+    ContentHash,
+    SettingsHash,
+    Kind,
+    Reason,
+}
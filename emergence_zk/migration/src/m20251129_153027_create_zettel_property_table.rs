@@ -0,0 +1,64 @@
+use crate::m20251104_024116_create_zettel_table::Zettel;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ZettelProperty::Table)
+                    .if_not_exists()
+                    .col(pk_auto(ZettelProperty::Id).not_null())
+                    .col(string(ZettelProperty::ZettelNanoId).not_null())
+                    .col(string(ZettelProperty::Key).not_null())
+                    // lists are stored as one row per item
+                    .col(text_null(ZettelProperty::Value))
+                    .col(string(ZettelProperty::Kind).not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-zettel_property-zettel_nano_id")
+                            .from(ZettelProperty::Table, ZettelProperty::ZettelNanoId)
+                            .to(Zettel::Table, Zettel::Nanoid)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-zettel_property-key-value")
+                    .table(ZettelProperty::Table)
+                    .col(ZettelProperty::Key)
+                    .col(ZettelProperty::Value)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ZettelProperty::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum ZettelProperty {
+    Table,
+    Id,
+    ZettelNanoId,
+    Key,
+    Value,
+    Kind,
+}
@@ -0,0 +1,57 @@
+use crate::m20251104_023917_create_tag_table::Tag;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const INDEX_NAME: &str = "idx-tag-name";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // the name was never unique inside the db, so duplicates have to be folded into the
+        // oldest row of the same name before the index can exist
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "UPDATE OR IGNORE zettel_tag SET tag_nano_id = (
+                SELECT keep.nanoid FROM tag AS dup JOIN tag AS keep ON keep.name = dup.name
+                WHERE dup.nanoid = zettel_tag.tag_nano_id ORDER BY keep.id LIMIT 1
+            )",
+        )
+        .await?;
+        db.execute_unprepared(
+            "UPDATE tag SET parent_nano_id = (
+                SELECT keep.nanoid FROM tag AS dup JOIN tag AS keep ON keep.name = dup.name
+                WHERE dup.nanoid = tag.parent_nano_id ORDER BY keep.id LIMIT 1
+            ) WHERE parent_nano_id IS NOT NULL",
+        )
+        .await?;
+        db.execute_unprepared(
+            "DELETE FROM zettel_tag WHERE tag_nano_id NOT IN (
+                SELECT nanoid FROM tag WHERE id IN (SELECT MIN(id) FROM tag GROUP BY name)
+            )",
+        )
+        .await?;
+        db.execute_unprepared(
+            "DELETE FROM tag WHERE id NOT IN (SELECT MIN(id) FROM tag GROUP BY name)",
+        )
+        .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(INDEX_NAME)
+                    .table(Tag::Table)
+                    .col(Tag::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(Index::drop().name(INDEX_NAME).table(Tag::Table).to_owned())
+            .await
+    }
+}
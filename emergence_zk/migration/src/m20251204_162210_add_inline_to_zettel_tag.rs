@@ -0,0 +1,36 @@
+use crate::m20251104_050736_create_zettel_tag_table::ZettelTag;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // whether the zettel only uses the tag as a `#tag` inside its content
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ZettelTag::Table)
+                    .add_column(boolean(ZettelTagInline::Inline).default(false))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ZettelTag::Table)
+                    .drop_column(ZettelTagInline::Inline)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ZettelTagInline {
+    Inline,
+}
@@ -0,0 +1,416 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use petgraph::unionfind::UnionFind;
+use serde::{Deserialize, Serialize};
+
+use crate::{ZettelId, ZkGraph};
+
+/// How much of its rank a zettel passes on along its links, the usual PageRank damping factor
+const DAMPING: f64 = 0.85;
+/// PageRank stops once the ranks change less than this in total between two rounds
+const PAGE_RANK_TOLERANCE: f64 = 1e-9;
+const MAX_ROUNDS: usize = 100;
+
+/// How a single zettel sits inside the graph of links.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NoteStats {
+    pub id: ZettelId,
+    /// How many other zettels link to this one
+    pub in_degree: usize,
+    /// How many other zettels this one links to
+    pub out_degree: usize,
+    /// Share of PageRank, all of them add up to 1
+    pub page_rank: f64,
+    /// Share of the shortest paths between two other zettels that pass through this one,
+    /// following links in either direction. 0 for zettels no path has to pass through, 1 for
+    /// zettels every path has to pass through.
+    pub betweenness: f64,
+}
+
+impl NoteStats {
+    /// Whether no link leads to or away from this zettel.
+    pub fn is_orphan(&self) -> bool {
+        self.in_degree == 0 && self.out_degree == 0
+    }
+
+    /// Whether this zettel is linked to, but doesn't link anywhere itself.
+    pub fn is_dead_end(&self) -> bool {
+        self.in_degree > 0 && self.out_degree == 0
+    }
+}
+
+/// Where the thinking inside of a kasten is well connected and where it is fragmented.
+///
+/// Links to the zettel itself are ignored, as are repeated links between the same two zettels.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GraphAnalytics {
+    /// Every zettel of the graph, sorted by id
+    pub notes: Vec<NoteStats>,
+    /// Groups of zettels that are linked together in either direction, largest first
+    pub components: Vec<Vec<ZettelId>>,
+    /// Groups of zettels that link to each other more than to the rest of their component,
+    /// largest first
+    pub communities: Vec<Vec<ZettelId>>,
+}
+
+impl GraphAnalytics {
+    /// Analyses the links between the zettels of `graph`.
+    pub fn of(graph: &ZkGraph) -> Self {
+        let mut ids = graph
+            .nodes_iter()
+            .map(|(_, node)| node.payload().id.clone())
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+
+        let links = graph.edges_iter().map(|(_, edge)| {
+            let link = edge.payload();
+            (&link.source, &link.dest)
+        });
+        let links = Links::new(&ids, links);
+
+        let page_rank = links.page_rank();
+        let betweenness = links.betweenness();
+        let notes = ids
+            .iter()
+            .enumerate()
+            .map(|(idx, id)| NoteStats {
+                id: id.clone(),
+                in_degree: links.incoming[idx].len(),
+                out_degree: links.outgoing[idx].len(),
+                page_rank: page_rank[idx],
+                betweenness: betweenness[idx],
+            })
+            .collect();
+
+        let group = |labels: Vec<usize>| {
+            let mut groups = BTreeMap::<usize, Vec<ZettelId>>::new();
+            for (idx, label) in labels.into_iter().enumerate() {
+                groups.entry(label).or_default().push(ids[idx].clone());
+            }
+            let mut groups = groups.into_values().collect::<Vec<_>>();
+            // the ids inside of a group are sorted, so ties are broken by the smallest id
+            groups.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a[0].cmp(&b[0])));
+            groups
+        };
+
+        Self {
+            notes,
+            components: group(links.components()),
+            communities: group(links.communities()),
+        }
+    }
+
+    /// The stats of the zettel with `id`, if it is part of the graph.
+    pub fn note(&self, id: &ZettelId) -> Option<&NoteStats> {
+        self.notes
+            .binary_search_by(|note| note.id.cmp(id))
+            .ok()
+            .map(|idx| &self.notes[idx])
+    }
+
+    /// Zettels without any links to or from them.
+    pub fn orphans(&self) -> impl Iterator<Item = &NoteStats> {
+        self.notes.iter().filter(|note| note.is_orphan())
+    }
+
+    /// Zettels that are linked to, but don't link anywhere themselves.
+    pub fn dead_ends(&self) -> impl Iterator<Item = &NoteStats> {
+        self.notes.iter().filter(|note| note.is_dead_end())
+    }
+
+    /// The `n` zettels most other zettels link to, ties broken by PageRank.
+    pub fn hubs(&self, n: usize) -> Vec<&NoteStats> {
+        let mut hubs = self
+            .notes
+            .iter()
+            .filter(|note| note.in_degree > 0)
+            .collect::<Vec<_>>();
+        hubs.sort_by(|a, b| {
+            b.in_degree
+                .cmp(&a.in_degree)
+                .then_with(|| b.page_rank.total_cmp(&a.page_rank))
+        });
+        hubs.truncate(n);
+        hubs
+    }
+
+    /// The `n` zettels with the highest PageRank.
+    pub fn by_page_rank(&self, n: usize) -> Vec<&NoteStats> {
+        let mut ranked = self.notes.iter().collect::<Vec<_>>();
+        ranked.sort_by(|a, b| b.page_rank.total_cmp(&a.page_rank));
+        ranked.truncate(n);
+        ranked
+    }
+
+    /// The `n` zettels the most shortest paths between other zettels pass through, the ones
+    /// bridging otherwise separate parts of the kasten.
+    pub fn bridges(&self, n: usize) -> Vec<&NoteStats> {
+        let mut bridges = self
+            .notes
+            .iter()
+            .filter(|note| note.betweenness > 0.0)
+            .collect::<Vec<_>>();
+        bridges.sort_by(|a, b| b.betweenness.total_cmp(&a.betweenness));
+        bridges.truncate(n);
+        bridges
+    }
+}
+
+/// The links of the graph as plain adjacency lists, indexed by the position of a zettel inside
+/// of the sorted ids.
+struct Links {
+    outgoing: Vec<Vec<usize>>,
+    incoming: Vec<Vec<usize>>,
+    /// `outgoing` and `incoming` together
+    neighbours: Vec<Vec<usize>>,
+}
+
+impl Links {
+    fn new<'a>(
+        ids: &[ZettelId],
+        links: impl Iterator<Item = (&'a ZettelId, &'a ZettelId)>,
+    ) -> Self {
+        let idx_of = ids
+            .iter()
+            .enumerate()
+            .map(|(idx, id)| (id, idx))
+            .collect::<HashMap<_, _>>();
+
+        let pairs = links
+            .filter_map(|(source, dest)| Some((*idx_of.get(source)?, *idx_of.get(dest)?)))
+            .filter(|(source, dest)| source != dest)
+            .collect::<HashSet<_>>();
+
+        let mut outgoing = vec![vec![]; ids.len()];
+        let mut incoming = vec![vec![]; ids.len()];
+        let mut neighbours = vec![vec![]; ids.len()];
+        for (source, dest) in pairs {
+            outgoing[source].push(dest);
+            incoming[dest].push(source);
+            neighbours[source].push(dest);
+            neighbours[dest].push(source);
+        }
+        // the pairs came out of a set, this keeps every result the same between runs
+        for list in outgoing
+            .iter_mut()
+            .chain(&mut incoming)
+            .chain(&mut neighbours)
+        {
+            list.sort_unstable();
+            list.dedup();
+        }
+
+        Self {
+            outgoing,
+            incoming,
+            neighbours,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.outgoing.len()
+    }
+
+    /// PageRank along the direction of the links. Zettels without links spread their rank
+    /// evenly over every zettel.
+    fn page_rank(&self) -> Vec<f64> {
+        let n = self.len();
+        if n == 0 {
+            return vec![];
+        }
+        let share = 1.0 / n as f64;
+        let mut ranks = vec![share; n];
+
+        for _ in 0..MAX_ROUNDS {
+            let dangling = (0..n)
+                .filter(|&idx| self.outgoing[idx].is_empty())
+                .map(|idx| ranks[idx])
+                .sum::<f64>();
+            let base = (1.0 - DAMPING) * share + DAMPING * dangling * share;
+
+            let next = (0..n)
+                .map(|idx| {
+                    base + DAMPING
+                        * self.incoming[idx]
+                            .iter()
+                            .map(|&source| ranks[source] / self.outgoing[source].len() as f64)
+                            .sum::<f64>()
+                })
+                .collect::<Vec<_>>();
+
+            let change = next
+                .iter()
+                .zip(&ranks)
+                .map(|(next, rank)| (next - rank).abs())
+                .sum::<f64>();
+            ranks = next;
+            if change < PAGE_RANK_TOLERANCE {
+                break;
+            }
+        }
+
+        ranks
+    }
+
+    /// Betweenness centrality following links in either direction, with Brandes' algorithm.
+    fn betweenness(&self) -> Vec<f64> {
+        let n = self.len();
+        let mut centrality = vec![0.0; n];
+
+        for start in 0..n {
+            let mut order = vec![];
+            let mut predecessors = vec![vec![]; n];
+            let mut paths = vec![0.0_f64; n];
+            let mut distance = vec![usize::MAX; n];
+            paths[start] = 1.0;
+            distance[start] = 0;
+
+            let mut queue = VecDeque::from([start]);
+            while let Some(current) = queue.pop_front() {
+                order.push(current);
+                for &next in &self.neighbours[current] {
+                    if distance[next] == usize::MAX {
+                        distance[next] = distance[current] + 1;
+                        queue.push_back(next);
+                    }
+                    if distance[next] == distance[current] + 1 {
+                        paths[next] += paths[current];
+                        predecessors[next].push(current);
+                    }
+                }
+            }
+
+            let mut dependency = vec![0.0; n];
+            for &current in order.iter().rev() {
+                for &previous in &predecessors[current] {
+                    dependency[previous] +=
+                        paths[previous] / paths[current] * (1.0 + dependency[current]);
+                }
+                if current != start {
+                    centrality[current] += dependency[current];
+                }
+            }
+        }
+
+        // every pair was counted from both ends
+        if n > 2 {
+            let pairs = ((n - 1) * (n - 2)) as f64;
+            for value in &mut centrality {
+                *value /= pairs;
+            }
+        }
+        centrality
+    }
+
+    /// Labels every zettel with the smallest index of the zettels it is connected to.
+    fn components(&self) -> Vec<usize> {
+        let mut sets = UnionFind::new(self.len());
+        for (source, dests) in self.outgoing.iter().enumerate() {
+            for &dest in dests {
+                sets.union(source, dest);
+            }
+        }
+
+        let mut labels = HashMap::new();
+        (0..self.len())
+            .map(|idx| *labels.entry(sets.find(idx)).or_insert(idx))
+            .collect()
+    }
+
+    /// Greedily moves every zettel into the community of its neighbours that raises the
+    /// modularity the most, until no move raises it anymore. This is the first phase of the
+    /// Louvain method, links count in either direction.
+    fn communities(&self) -> Vec<usize> {
+        let mut labels = (0..self.len()).collect::<Vec<_>>();
+        let degrees = self
+            .neighbours
+            .iter()
+            .map(|neighbours| neighbours.len() as f64)
+            .collect::<Vec<_>>();
+        // the number of links, each of them adds to two degrees
+        let m = degrees.iter().sum::<f64>() / 2.0;
+        if m == 0.0 {
+            return labels;
+        }
+        // the summed degrees of every zettel inside a community
+        let mut totals = degrees.clone();
+
+        for _ in 0..MAX_ROUNDS {
+            let mut moved = false;
+            for idx in 0..self.len() {
+                let current = labels[idx];
+                totals[current] -= degrees[idx];
+
+                // links from this zettel into each community it could join
+                let mut inside = BTreeMap::from([(current, 0.0)]);
+                for &neighbour in &self.neighbours[idx] {
+                    *inside.entry(labels[neighbour]).or_default() += 1.0;
+                }
+                let gain = |label: usize, links: f64| {
+                    links / m - totals[label] * degrees[idx] / (2.0 * m * m)
+                };
+
+                let mut best = (current, gain(current, inside[&current]));
+                for (&label, &links) in &inside {
+                    let gain = gain(label, links);
+                    if gain > best.1 {
+                        best = (label, gain);
+                    }
+                }
+
+                labels[idx] = best.0;
+                totals[best.0] += degrees[idx];
+                moved |= best.0 != current;
+            }
+            if !moved {
+                break;
+            }
+        }
+
+        labels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ZettelId;
+
+    use super::Links;
+
+    fn links(n: usize, pairs: &[(usize, usize)]) -> Links {
+        let ids = (0..n)
+            .map(|idx| ZettelId::from(format!("{idx:02}").as_str()))
+            .collect::<Vec<_>>();
+        let pairs = pairs
+            .iter()
+            .map(|&(source, dest)| (&ids[source], &ids[dest]))
+            .collect::<Vec<_>>();
+        Links::new(&ids, pairs.into_iter())
+    }
+
+    #[test]
+    fn test_page_rank() {
+        // everything points at 0, which points back at 1
+        let ranks = links(4, &[(1, 0), (2, 0), (3, 0), (0, 1)]).page_rank();
+        assert!((ranks.iter().sum::<f64>() - 1.0).abs() < 1e-6);
+        assert!(ranks[0] > ranks[1] && ranks[1] > ranks[2]);
+        assert!((ranks[2] - ranks[3]).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_bridges_and_communities() {
+        // two triangles joined by 2 - 3
+        let links = links(6, &[(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 5), (5, 3)]);
+        let centrality = links.betweenness();
+        assert_eq!(centrality[0], 0.0);
+        assert!(centrality[2] > 0.0);
+        assert_eq!(centrality[2], centrality[3]);
+
+        let communities = links.communities();
+        assert_eq!(communities[0], communities[1]);
+        assert_eq!(communities[1], communities[2]);
+        assert_eq!(communities[3], communities[4]);
+        assert_ne!(communities[0], communities[5]);
+    }
+}
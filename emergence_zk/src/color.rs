@@ -0,0 +1,180 @@
+use std::{collections::HashSet, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ZkError;
+
+/// An RGB color, written as `#rrggbb`.
+///
+/// Parses from `#rrggbb`, `#rgb` (the `#` is optional) and `rgb(r, g, b)`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Colors new tags are given, picked to be easy to tell apart from one another
+    pub const PALETTE: [Color; 12] = [
+        Color::rgb(0x4e, 0x79, 0xa7),
+        Color::rgb(0xf2, 0x8e, 0x2b),
+        Color::rgb(0xe1, 0x57, 0x59),
+        Color::rgb(0x76, 0xb7, 0xb2),
+        Color::rgb(0x59, 0xa1, 0x4f),
+        Color::rgb(0xed, 0xc9, 0x48),
+        Color::rgb(0xb0, 0x7a, 0xa1),
+        Color::rgb(0xff, 0x9d, 0xa7),
+        Color::rgb(0x9c, 0x75, 0x5f),
+        Color::rgb(0xba, 0xb0, 0xac),
+        Color::rgb(0x8c, 0xd1, 0x7d),
+        Color::rgb(0x49, 0x98, 0x94),
+    ];
+
+    /// The palette color belonging to `name`, always the same one for the same name.
+    pub fn from_palette(name: &str) -> Self {
+        Self::PALETTE[Self::palette_index(name)]
+    }
+
+    /// Like [`Color::from_palette`], but skips ahead to the next palette color nothing in
+    /// `used` has yet. Once every palette color is used, this is the same as
+    /// [`Color::from_palette`].
+    pub fn from_palette_avoiding(name: &str, used: &HashSet<Color>) -> Self {
+        let start = Self::palette_index(name);
+        (0..Self::PALETTE.len())
+            .map(|offset| Self::PALETTE[(start + offset) % Self::PALETTE.len()])
+            .find(|color| !used.contains(color))
+            .unwrap_or(Self::PALETTE[start])
+    }
+
+    /// FNV-1a, since the hashers of std may change between releases
+    fn palette_index(name: &str) -> usize {
+        let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+        (hash % Self::PALETTE.len() as u64) as usize
+    }
+}
+
+impl FromStr for Color {
+    type Err = ZkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            ZkError::ParseError(format!(
+                "{s:?} isn't a color like #4e79a7, #47a or rgb(78, 121, 167)"
+            ))
+        };
+        let s = s.trim().to_lowercase();
+
+        if let Some(channels) = s
+            .strip_prefix("rgb(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            let channels = channels
+                .split(',')
+                .map(|channel| channel.trim().parse::<u8>().map_err(|_| invalid()))
+                .collect::<Result<Vec<_>, _>>()?;
+            let [r, g, b] = channels[..] else {
+                return Err(invalid());
+            };
+            return Ok(Self::rgb(r, g, b));
+        }
+
+        let hex = s.strip_prefix('#').unwrap_or(&s);
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let channel = |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| invalid());
+
+        match hex.len() {
+            6 => Ok(Self::rgb(
+                channel(&hex[0..2])?,
+                channel(&hex[2..4])?,
+                channel(&hex[4..6])?,
+            )),
+            // `#47a` is short for `#4477aa`
+            3 => Ok(Self::rgb(
+                channel(&hex[0..1])? * 0x11,
+                channel(&hex[1..2])? * 0x11,
+                channel(&hex[2..3])? * 0x11,
+            )),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = ZkError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Color> for String {
+    fn from(value: Color) -> Self {
+        value.to_string()
+    }
+}
+
+impl From<Color> for ecolor::Color32 {
+    fn from(value: Color) -> Self {
+        ecolor::Color32::from_rgb(value.r, value.g, value.b)
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::Color;
+
+    #[test]
+    fn test_parse() {
+        let color = Color::rgb(0x44, 0x77, 0xaa);
+        for input in ["#4477aa", "4477AA", "#47a", " rgb(68, 119,170) "] {
+            assert_eq!(input.parse::<Color>().expect("valid color"), color);
+        }
+        assert_eq!(color.to_string(), "#4477aa");
+
+        for input in [
+            "",
+            "random",
+            "#12345",
+            "#ggg",
+            "rgb(1, 2)",
+            "rgb(1, 2, 256)",
+        ] {
+            assert!(input.parse::<Color>().is_err(), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn test_palette() {
+        assert_eq!(Color::from_palette("rust"), Color::from_palette("rust"));
+
+        let mut used = HashSet::new();
+        for name in Color::PALETTE.iter().map(|color| color.to_string()) {
+            used.insert(Color::from_palette_avoiding(&name, &used));
+        }
+        for color in Color::PALETTE {
+            assert!(used.contains(&color));
+        }
+        assert_eq!(
+            Color::from_palette_avoiding("rust", &used),
+            Color::from_palette("rust")
+        );
+    }
+}
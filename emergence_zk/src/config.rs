@@ -0,0 +1,245 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use chrono::{NaiveDate, NaiveDateTime, format::Item, format::StrftimeItems};
+use serde::{Deserialize, Serialize};
+
+use crate::{Color, ZkError, ZkResult, id, zettel::DATE_FMT_STR};
+
+/// Name of the config file inside of the `.emergence` folder of a kasten
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Settings of a single kasten, read from `.emergence/config.toml`. Every key is optional and
+/// falls back to its default, so a missing file is the same as an empty one.
+///
+/// ```toml
+/// date_format = "%Y-%m-%d %H:%M:%S"
+/// id_length = 12
+/// id_alphabet = "abcdefghijklmnopqrstuvwxyz0123456789"
+/// default_tag_color = "#4e79a7"
+/// ignored_paths = ["templates", "archive/old"]
+/// editor = "nvim"
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KastenConfig {
+    /// strftime format the date of legacy front matter is written in
+    pub date_format: String,
+    /// Number of characters in newly generated ids
+    pub id_length: usize,
+    /// Characters newly generated ids are made of
+    pub id_alphabet: String,
+    /// Color new tags get, either a color like `#4e79a7` or `palette` to give every tag its own
+    pub default_tag_color: String,
+    /// Files and directories, relative to the root of the kasten, that aren't part of it
+    pub ignored_paths: Vec<PathBuf>,
+    /// Command zettels are opened with, falls back to `$EDITOR`, then `$VISUAL`, then `vim`
+    pub editor: Option<String>,
+}
+
+impl Default for KastenConfig {
+    fn default() -> Self {
+        Self {
+            date_format: DATE_FMT_STR.to_owned(),
+            id_length: id::DEFAULT_LENGTH,
+            id_alphabet: id::DEFAULT_ALPHABET.iter().collect(),
+            default_tag_color: PALETTE_TAG_COLOR.to_owned(),
+            ignored_paths: vec![],
+            editor: None,
+        }
+    }
+}
+
+/// Value of `default_tag_color` that gives every new tag a color of the [palette](Color::PALETTE)
+pub const PALETTE_TAG_COLOR: &str = "palette";
+
+/// Smallest id length we allow, anything shorter collides way too quickly
+const MIN_ID_LENGTH: usize = 4;
+/// Largest id length we allow, ids end up in file names and links
+const MAX_ID_LENGTH: usize = 64;
+
+impl KastenConfig {
+    /// Returns the path of the config file of the kasten at `root`.
+    pub fn path(root: &Path) -> PathBuf {
+        root.join(".emergence").join(CONFIG_FILE_NAME)
+    }
+
+    /// Loads the config of the kasten at `root`, if there is no config file the defaults are
+    /// used.
+    ///
+    /// # Errors
+    /// Errors if the file can't be read, isn't valid toml or holds invalid settings.
+    pub fn load(root: &Path) -> ZkResult<Self> {
+        let path = Self::path(root);
+
+        let string = match fs::read_to_string(&path) {
+            Ok(string) => string,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let config: Self = toml::from_str(&string)
+            .map_err(|e| ZkError::ConfigError(format!("{}: {e}", path.display())))?;
+
+        config
+            .validate()
+            .map_err(|e| ZkError::ConfigError(format!("{}: {e}", path.display())))?;
+
+        Ok(config)
+    }
+
+    /// Writes the config of the kasten at `root` to its config file.
+    pub fn store(&self, root: &Path) -> ZkResult<()> {
+        let string =
+            toml::to_string_pretty(self).map_err(|e| ZkError::ConfigError(e.to_string()))?;
+        fs::write(Self::path(root), string)?;
+        Ok(())
+    }
+
+    /// Checks that every setting is usable, returning what is wrong otherwise.
+    pub fn validate(&self) -> Result<(), String> {
+        if StrftimeItems::new(&self.date_format).any(|item| item == Item::Error) {
+            return Err(format!(
+                "date_format {:?} isn't a valid format",
+                self.date_format
+            ));
+        }
+
+        // the date has to survive being written and read back in
+        let probe = NaiveDate::from_ymd_opt(2025, 1, 1)
+            .and_then(|date| date.and_hms_opt(13, 37, 0))
+            .expect("valid date");
+        let written = probe.format(&self.date_format).to_string();
+        if NaiveDateTime::parse_from_str(&written, &self.date_format).is_err() {
+            return Err(format!(
+                "date_format {:?} has to contain a full date and time",
+                self.date_format
+            ));
+        }
+
+        if !(MIN_ID_LENGTH..=MAX_ID_LENGTH).contains(&self.id_length) {
+            return Err(format!(
+                "id_length has to be between {MIN_ID_LENGTH} and {MAX_ID_LENGTH}, not {}",
+                self.id_length
+            ));
+        }
+
+        // ids are file names and link targets
+        if let Some(c) = self
+            .id_alphabet
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+        {
+            return Err(format!("id_alphabet can't contain {c:?}"));
+        }
+
+        let unique = self.id_alphabet.chars().collect::<HashSet<_>>();
+        if unique.len() != self.id_alphabet.chars().count() {
+            return Err("id_alphabet can't contain a character twice".to_owned());
+        }
+        if unique.len() < 2 {
+            return Err("id_alphabet needs at least 2 characters".to_owned());
+        }
+
+        if self.default_tag_color.trim().is_empty() {
+            return Err("default_tag_color can't be empty".to_owned());
+        }
+        if let Err(e) = self.fixed_tag_color() {
+            return Err(format!("default_tag_color is invalid: {e}"));
+        }
+
+        if let Some(path) = self.ignored_paths.iter().find(|path| path.is_absolute()) {
+            return Err(format!(
+                "ignored_paths have to be relative to the kasten, {} isn't",
+                path.display()
+            ));
+        }
+
+        if self
+            .editor
+            .as_ref()
+            .is_some_and(|editor| editor.trim().is_empty())
+        {
+            return Err("editor can't be empty".to_owned());
+        }
+
+        Ok(())
+    }
+
+    /// Returns the color every new tag gets, `None` if they get one of the
+    /// [palette](Color::PALETTE) instead. `random` is what older kastens were created with, it
+    /// means the same as `palette`.
+    ///
+    /// # Errors
+    /// Errors if `default_tag_color` is neither of those nor a valid color.
+    pub fn fixed_tag_color(&self) -> ZkResult<Option<Color>> {
+        match self.default_tag_color.trim() {
+            PALETTE_TAG_COLOR | "random" => Ok(None),
+            color => color.parse().map(Some),
+        }
+    }
+
+    /// Returns whether `relative`, a path relative to the root of the kasten, lies inside of one
+    /// of the ignored paths.
+    pub fn is_ignored(&self, relative: &Path) -> bool {
+        self.ignored_paths
+            .iter()
+            .any(|ignored| relative.starts_with(ignored))
+    }
+
+    /// Returns the command that opens `path` in the configured editor.
+    pub fn editor_command(&self, path: impl AsRef<Path>) -> Command {
+        let editor = self
+            .editor
+            .clone()
+            .or_else(|| std::env::var("EDITOR").ok())
+            .or_else(|| std::env::var("VISUAL").ok())
+            .filter(|editor| !editor.trim().is_empty())
+            .unwrap_or_else(|| "vim".to_owned());
+
+        // editors are commonly configured with arguments, like `code --wait`
+        let mut parts = editor.split_whitespace();
+        let mut command = Command::new(parts.next().expect("editor isn't empty"));
+        command.args(parts).arg(path.as_ref());
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::KastenConfig;
+
+    #[test]
+    fn defaults_are_valid() {
+        assert_eq!(KastenConfig::default().validate(), Ok(()));
+
+        let config: KastenConfig = toml::from_str("").expect("empty config is valid");
+        assert_eq!(config, KastenConfig::default());
+    }
+
+    #[test]
+    fn validation() {
+        let invalid = [
+            "date_format = \"%Y-%m-%d\"",
+            "date_format = \"%Q\"",
+            "id_length = 2",
+            "id_alphabet = \"aa\"",
+            "id_alphabet = \"ab/\"",
+            "default_tag_color = \"\"",
+            "default_tag_color = \"blue\"",
+            "ignored_paths = [\"/etc\"]",
+            "editor = \" \"",
+        ];
+
+        for config in invalid {
+            let config: KastenConfig = toml::from_str(config).expect("valid toml");
+            assert!(config.validate().is_err(), "{config:?} should be invalid");
+        }
+
+        assert!(toml::from_str::<KastenConfig>("id_lenght = 12").is_err());
+    }
+}
@@ -2,6 +2,8 @@
 
 pub mod prelude;
 
+pub mod link;
 pub mod tag;
 pub mod zettel;
+pub mod zettel_property;
 pub mod zettel_tag;
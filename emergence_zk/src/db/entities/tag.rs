@@ -13,6 +13,7 @@ pub struct Model {
     #[sea_orm(unique)]
     pub nanoid: String,
     pub color: String,
+    pub parent_nano_id: Option<String>,
     #[sea_orm(has_many, via = "zettel_tag")]
     pub zettels: HasMany<super::zettel::Entity>,
 }
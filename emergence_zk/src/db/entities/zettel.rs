@@ -11,6 +11,11 @@ pub struct Model {
     #[sea_orm(unique)]
     pub nanoid: String,
     pub title: String,
+    pub mtime: Option<i64>,
+    pub content_hash: Option<String>,
+    pub settings_hash: Option<String>,
+    /// Path of the zettel's file, relative to the root of the kasten
+    pub path: Option<String>,
     #[sea_orm(has_many, via = "zettel_tag")]
     pub tags: HasMany<super::tag::Entity>,
 }
@@ -10,6 +10,7 @@ pub struct Model {
     pub zettel_nano_id: String,
     #[sea_orm(primary_key, auto_increment = false)]
     pub tag_nano_id: String,
+    pub inline: bool,
     #[sea_orm(
         belongs_to,
         from = "tag_nano_id",
@@ -0,0 +1,136 @@
+use std::{fmt::Display, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Tag, Zettel, ZkError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Severity {
+    /// The zettel couldn't be parsed and is missing from the kasten
+    Error,
+    /// The zettel was parsed, but something inside of it looks wrong
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found while parsing a kasten, pointing at the place inside of a file it comes from.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    /// 1-based line inside of the file
+    pub line: usize,
+    /// 1-based column inside of the line, counted in characters
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(
+        path: impl Into<PathBuf>,
+        line: usize,
+        column: usize,
+        severity: Severity,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            line,
+            column,
+            severity,
+            message: message.into(),
+        }
+    }
+
+    /// Turns the `error` that kept the zettel at `path` from being parsed into a diagnostic,
+    /// pointing at the start of the file if the error has no location.
+    pub fn from_error(path: impl Into<PathBuf>, error: &ZkError) -> Self {
+        match error {
+            ZkError::ParseErrorAt {
+                line,
+                column,
+                message,
+            } => Self::new(path, *line, *column, Severity::Error, message),
+            error => Self::new(path, 1, 1, Severity::Error, error.to_string()),
+        }
+    }
+
+    /// Returns warnings for everything that looks wrong inside of a parsed `zettel`, like links
+    /// that don't point anywhere or tags with invalid names.
+    pub fn for_zettel(zettel: &Zettel) -> Vec<Self> {
+        let invalid_tags = zettel
+            .front_matter
+            .tag_strings
+            .iter()
+            .filter_map(|tag| Some((tag, Tag::parse_name(tag).err()?)))
+            .collect::<Vec<_>>();
+
+        if zettel.dangling_links.is_empty() && invalid_tags.is_empty() {
+            return vec![];
+        }
+
+        // the tags sit somewhere inside of the front matter, which is written back the way it
+        // was read
+        let front_matter = zettel.front_matter.to_string();
+        let tag_diagnostics = invalid_tags.into_iter().map(|(tag, e)| {
+            let (line, column) = front_matter
+                .lines()
+                .enumerate()
+                .find_map(|(idx, text)| {
+                    let text = text.to_lowercase();
+                    let column = text.find(tag.as_str())?;
+                    Some((idx + 1, text[..column].chars().count() + 1))
+                })
+                .unwrap_or((1, 1));
+
+            Self::new(&zettel.path, line, column, Severity::Warning, e.to_string())
+        });
+
+        zettel
+            .dangling_links
+            .iter()
+            .map(|link| {
+                let column = link
+                    .line
+                    .checked_sub(zettel.content_line_offset + 1)
+                    .and_then(|idx| zettel.content.lines().nth(idx))
+                    .and_then(|text| {
+                        let idx = text.find(&link.target)?;
+                        Some(text[..idx].chars().count() + 1)
+                    })
+                    .unwrap_or(1);
+
+                Self::new(
+                    &zettel.path,
+                    link.line,
+                    column,
+                    Severity::Warning,
+                    format!("broken link to {}: {}", link.target, link.reason),
+                )
+            })
+            .chain(tag_diagnostics)
+            .collect()
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}: {}",
+            self.path.display(),
+            self.line,
+            self.column,
+            self.severity,
+            self.message
+        )
+    }
+}
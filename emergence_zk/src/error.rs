@@ -3,15 +3,38 @@ use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum ZkError {
-    #[error("File error: ")]
+    #[error("File error: {0}")]
     FileError(#[from] io::Error),
 
-    #[error("Parse Error: ")]
+    #[error("Parse Error: {0}")]
     ParseError(String),
 
-    #[error("Database Error: ")]
+    /// A parse error at a 1-based `line` and `column` inside of a file
+    #[error("Parse Error at {line}:{column}: {message}")]
+    ParseErrorAt {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+
+    #[error("Database Error: {0}")]
     DbError(#[from] sea_orm::DbErr),
 
-    #[error("FS Watcher Error")]
+    /// The config of a kasten is unreadable or holds invalid settings
+    #[error("Config Error: {0}")]
+    ConfigError(String),
+
+    /// The folder isn't a kasten, or one with a layout we can't read
+    #[error("Kasten Layout Error: {0}")]
+    LayoutError(String),
+
+    #[error("No zettel with id {0}")]
+    ZettelNotFound(crate::ZettelId),
+
+    /// An operation on the kasten that can't be carried out as asked
+    #[error("Invalid Operation: {0}")]
+    InvalidOperation(String),
+
+    #[error("FS Watcher Error: {0}")]
     NotifyError(#[from] notify::Error),
 }
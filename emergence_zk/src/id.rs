@@ -5,17 +5,27 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::ZkError;
+use crate::{KastenConfig, ZkError};
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
 pub struct ZettelId(String);
 
-const ALPHABET: [char; 26] = [
+/// Characters ids are made of, unless the kasten is configured otherwise
+pub(crate) const DEFAULT_ALPHABET: [char; 26] = [
     'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
     't', 'u', 'v', 'w', 'x', 'y', 'z',
 ];
+/// Length of ids, unless the kasten is configured otherwise
+pub(crate) const DEFAULT_LENGTH: usize = 10;
 
 impl ZettelId {
+    /// Generates a new random id with the length and alphabet of `config`.
+    pub fn generate(config: &KastenConfig) -> Self {
+        let alphabet = config.id_alphabet.chars().collect::<Vec<_>>();
+        let length = config.id_length;
+        ZettelId(nanoid!(length, &alphabet))
+    }
+
     pub fn as_str(&self) -> &str {
         &self.0
     }
@@ -23,7 +33,7 @@ impl ZettelId {
 
 impl Default for ZettelId {
     fn default() -> Self {
-        ZettelId(nanoid!(10, &ALPHABET))
+        ZettelId(nanoid!(DEFAULT_LENGTH, &DEFAULT_ALPHABET))
     }
 }
 
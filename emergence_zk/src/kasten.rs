@@ -1,37 +1,151 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap, HashSet},
+    ffi::OsStr,
     fs::{self},
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
 
+use chrono::NaiveDateTime;
 use notify::{
     Config, EventKind, RecommendedWatcher,
     event::{ModifyKind, RemoveKind},
 };
 use notify::{RecursiveMode, Watcher};
 use petgraph::{Directed, prelude::NodeIndex, prelude::StableGraph};
-use rayon::prelude::*;
-use tokio::{sync::mpsc::channel, time::Instant};
+use tokio::{sync::mpsc::channel, task::JoinHandle, time::Instant};
 use tracing::{error, info, warn};
+use walkdir::WalkDir;
 
-use crate::{Link, Workspace, Zettel, ZettelId, ZkResult};
+use crate::{
+    Backlink, BacklinkIndex, DanglingLink, Diagnostic, ExternalLink, FileStamp, GraphAnalytics,
+    KastenConfig, KastenMetadata, Link, PropertyFilter, Query, Relation, SearchHit, Severity,
+    Split, StoredLinks, Tag, TagNode, TagRegistry, UsedColors, Workspace, Zettel, ZettelBuilder,
+    ZettelId, ZkError, ZkResult, apply_edits,
+    entities::{prelude::*, zettel},
+    is_within,
+};
 use egui_graphs::Graph;
 
 pub type ZkGraph = Graph<Zettel, Link, Directed>;
 
 #[derive(Debug, Clone)]
 pub struct Kasten {
+    /// Stable id of the kasten, see [`KastenMetadata`]
     pub id: ZettelId,
     pub name: String,
+    pub created_at: NaiveDateTime,
     pub graph: ZkGraph,
     pub ws: Workspace,
     pub zid_to_gid: HashMap<ZettelId, NodeIndex>,
+    pub backlink_index: BacklinkIndex,
+    /// Problems found while parsing, like zettels that couldn't be parsed or broken links
+    pub diagnostics: Vec<Diagnostic>,
     pub most_recently_edited: Option<NodeIndex>,
 }
 
 pub type KastenHandle = Arc<Mutex<Kasten>>;
 
+/// A zettel read during `Kasten::parse`, along with what the db had cached for it.
+struct ParsedZettel {
+    zettel: Zettel,
+    stamp: FileStamp,
+    /// The stamp the db had for this zettel, `None` if it was never fully parsed
+    stored_stamp: Option<FileStamp>,
+    /// The title the db had for this zettel, `None` if the db didn't know about it
+    stored_title: Option<String>,
+    /// Whether the file is the same, at the same place and read with the same settings, as when
+    /// the db cache was written
+    unchanged: bool,
+}
+
+impl ParsedZettel {
+    /// Parses the zettel at `path`, only going through the db if the file changed since the
+    /// cached `row` and `links` were stored. New tags pick their colors out of `used_colors`.
+    async fn parse(
+        path: PathBuf,
+        row: Option<zettel::ModelEx>,
+        links: StoredLinks,
+        ws: &Workspace,
+        used_colors: &UsedColors,
+    ) -> ZkResult<Self> {
+        let stored_stamp = row.as_ref().and_then(|row| {
+            FileStamp::from_row(
+                row.mtime,
+                row.content_hash.clone(),
+                row.settings_hash.clone(),
+            )
+        });
+        let stored_title = row.as_ref().map(|row| row.title.clone());
+
+        let stamp = FileStamp::read_against(&path, &ws.config, stored_stamp.as_ref())?;
+
+        // a moved zettel has to be re-processed, as its relative links might point elsewhere now
+        let relative_path = ws.relative_path(&path).to_string_lossy().into_owned();
+        let unchanged = stored_stamp
+            .as_ref()
+            .is_some_and(|stored| stored.matches(&stamp))
+            && row
+                .as_ref()
+                .is_some_and(|row| row.path.as_ref() == Some(&relative_path));
+
+        let zettel = match row {
+            Some(row) if unchanged => {
+                let tags = row.tags.into_iter().map(Tag::from).collect();
+                Zettel::from_cache(path, tags, links, &ws.config)?
+            }
+            _ => Zettel::from_path_unlinked(path, ws, used_colors).await?,
+        };
+
+        Ok(Self {
+            zettel,
+            stamp,
+            stored_stamp,
+            stored_title,
+            unchanged,
+        })
+    }
+}
+
+/// Returns whether `file_name` belongs to a hidden file or directory, like `.emergence`.
+fn is_hidden(file_name: &OsStr) -> bool {
+    file_name.to_string_lossy().starts_with('.')
+}
+
+/// Returns whether `path` is a markdown file the kasten would parse, one that isn't inside of a
+/// hidden or ignored directory.
+fn is_part_of_kasten(ws: &Workspace, path: &Path) -> bool {
+    let relative = ws.relative_path(path);
+    relative.extension().is_some_and(|ext| ext == "md")
+        && !(relative.iter().any(is_hidden) || ws.config.is_ignored(&relative))
+}
+
+/// Returns the path of every zettel file of the kasten, which can be organized into any number
+/// of (non hidden, non ignored) subdirectories.
+fn zettel_files(ws: &Workspace) -> impl Iterator<Item = PathBuf> {
+    WalkDir::new(&ws.root)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !(is_hidden(entry.file_name())
+                    || ws.config.is_ignored(&ws.relative_path(entry.path())))
+        })
+        .flatten()
+        .filter(|entry| {
+            entry.file_type().is_file()
+                && entry
+                    .path()
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .map(|ext| ext == "md")
+                    .unwrap_or(false)
+        })
+        .map(|entry| entry.into_path())
+}
+
+/// Name of the folder inside of `.emergence` deleted zettels are moved to
+pub const TRASH_DIR_NAME: &str = "trash";
+
 /// maximum number of nodes in our graph, setting at this arbitrary number because im not sure
 /// if the graph type has the capability to scale with adding more nodes
 const GRAPH_MAX_NODES: usize = 128;
@@ -39,12 +153,6 @@ const GRAPH_MAX_NODES: usize = 128;
 const GRAPH_MAX_EDGES: usize = GRAPH_MAX_NODES * 3;
 
 impl Kasten {
-    fn name_from_path_buf(path: PathBuf) -> String {
-        path.file_name()
-            .map(|os_str| os_str.to_string_lossy().into_owned())
-            .unwrap_or("ZettleKasten".to_owned())
-    }
-
     /// Creates a new kasten at the provided `dest`
     ///
     /// # Errors
@@ -57,7 +165,21 @@ impl Kasten {
         let mut our_folder = dest.clone();
         our_folder.push(".emergence");
 
-        fs::create_dir_all(our_folder)?;
+        fs::create_dir_all(&our_folder)?;
+
+        // write out the defaults, so there is something to edit
+        if !KastenConfig::path(&dest).exists() {
+            KastenConfig::default().store(&dest)?;
+        }
+
+        // creating a kasten where there already is one keeps its identity
+        let metadata = if KastenMetadata::path(&dest).exists() {
+            KastenMetadata::load(&dest)?
+        } else {
+            let metadata = KastenMetadata::new(&dest);
+            metadata.store(&dest)?;
+            metadata
+        };
 
         let graph: ZkGraph = ZkGraph::from(&StableGraph::with_capacity(
             GRAPH_MAX_NODES,
@@ -65,14 +187,16 @@ impl Kasten {
         ));
 
         let ws = Workspace::new(&dest).await?;
-        let id = ZettelId::default();
         // okay now we have a new thingy
         let me = Self {
-            id,
+            id: metadata.id,
+            name: metadata.name,
+            created_at: metadata.created_at,
             graph,
-            name: Self::name_from_path_buf(dest),
             ws,
             zid_to_gid: HashMap::new(),
+            backlink_index: BacklinkIndex::default(),
+            diagnostics: vec![],
             most_recently_edited: None,
         };
 
@@ -80,7 +204,8 @@ impl Kasten {
     }
 
     /// Parses a Kasten from the specified `root`.
-    /// NOTE: If any `Zettel` is unable to be parsed, it will be skipped instead of erroring out.
+    /// NOTE: If any `Zettel` is unable to be parsed, it is left out of the kasten instead of
+    /// erroring out, and the reason is recorded in [`Kasten::diagnostics`].
     ///
     /// # Errors
     /// This function can error if any file-system operation fails.  
@@ -88,40 +213,124 @@ impl Kasten {
         let start = Instant::now();
         let root = root.into();
 
+        // refuse layouts we don't understand before touching the db
+        let metadata = KastenMetadata::load(&root)?;
         let ws = Workspace::new(&root).await?;
 
-        let paths = fs::read_dir(&root)?
-            .par_bridge()
-            .flatten()
-            .filter(|entry| {
-                entry.file_type().map(|ft| ft.is_file()).unwrap_or(false)
-                    && entry
-                        .path()
-                        .extension()
-                        .and_then(|ext| ext.to_str())
-                        .map(|ext| ext == "md")
-                        .unwrap_or(false)
-            })
-            .map(|entry| entry.path())
-            .collect::<Vec<_>>();
+        let paths = zettel_files(&ws).collect::<Vec<_>>();
+
+        // load everything the db has cached up front, so unchanged zettels need no queries
+        let mut rows = ZettelEntity::load()
+            .with(TagEntity)
+            .all(ws.db.as_ref())
+            .await?
+            .into_iter()
+            .map(|row| (ZettelId::from(row.nanoid.as_str()), row))
+            .collect::<HashMap<_, _>>();
+        let mut stored_links = StoredLinks::load_all(&ws).await?;
 
-        // spawn all the zettel tasks
+        let mut diagnostics = vec![];
+
+        // spawn all the zettel tasks, new tags read the colors of the others only once
+        let used_colors = UsedColors::default();
+        let mut seen = HashSet::new();
         let zettel_tasks = paths
             .into_iter()
-            .map(|path| {
-                let ws = ws.clone(); // Clone Arc or whatever ws is
-                tokio::spawn(async move { Zettel::from_path(path, &ws).await })
+            .filter_map(|path| {
+                let id = ZettelId::try_from(path.as_path()).ok()?;
+                if !seen.insert(id.clone()) {
+                    warn!("another zettel already has the id {id}, skipping {path:?}");
+                    diagnostics.push(Diagnostic::new(
+                        &path,
+                        1,
+                        1,
+                        Severity::Error,
+                        format!("another zettel already has the id {id}"),
+                    ));
+                    return None;
+                }
+                let row = rows.remove(&id);
+                let links = stored_links.remove(&id).unwrap_or_default();
+                let ws = ws.clone();
+                let used_colors = used_colors.clone();
+                let task_path = path.clone();
+                Some((
+                    path,
+                    tokio::spawn(async move {
+                        ParsedZettel::parse(task_path, row, links, &ws, &used_colors).await
+                    }),
+                ))
             })
             .collect::<Vec<_>>();
 
         // await all of them
-        let zettels = futures::future::join_all(zettel_tasks)
-            .await
+        let parsed = Self::join_parse_tasks(zettel_tasks, &mut diagnostics).await;
+
+        // whatever is left in the db belongs to files that are gone
+        let mut pruned = !rows.is_empty();
+        for id in rows.keys() {
+            info!("removing zettel that no longer exists: {id}");
+            Zettel::remove_from_db(id, &ws).await?;
+        }
+
+        // zettels that couldn't be parsed are missing from the graph, so the db has to forget
+        // them as well, or searches and wikilinks would still find them
+        let parsed_ids = parsed.iter().map(|p| &p.zettel.id).collect::<HashSet<_>>();
+        for id in seen.iter().filter(|id| !parsed_ids.contains(id)) {
+            info!("removing zettel that couldn't be parsed: {id}");
+            Zettel::remove_from_db(id, &ws).await?;
+            pruned = true;
+        }
+
+        let changed = parsed
+            .iter()
+            .filter(|p| !p.unchanged)
+            .map(|p| p.zettel.id.clone())
+            .collect::<HashSet<_>>();
+
+        // adding, removing or retitling a zettel can change what any wikilink resolves to
+        let relink_all = pruned
+            || parsed
+                .iter()
+                .any(|p| p.stored_title.as_ref() != Some(&p.zettel.front_matter.title));
+
+        info!(
+            "{} of {} zettels changed since the last parse",
+            changed.len(),
+            parsed.len()
+        );
+
+        // every zettel is in the db now, so links can be resolved against all of their titles
+        let link_tasks = parsed
             .into_iter()
-            .filter_map(
-                |result| result.ok()?.ok(), // .map(|z| Arc::new(z) )
-            )
-            .collect::<Vec<Zettel>>();
+            .map(|mut parsed| {
+                let ws = ws.clone();
+                let relink = relink_all
+                    || changed.contains(&parsed.zettel.id)
+                    // anchors into a changed zettel might not point at a heading anymore
+                    || parsed.zettel.links.iter().any(|l| changed.contains(&l.dest));
+
+                let path = parsed.zettel.path.clone();
+                let task = tokio::spawn(async move {
+                    if relink {
+                        parsed.zettel.resolve_links(&ws).await?;
+                    }
+                    if parsed.stored_stamp.as_ref() != Some(&parsed.stamp) {
+                        parsed.stamp.store(&parsed.zettel.id, &ws).await?;
+                    }
+                    ZkResult::Ok(parsed.zettel)
+                });
+                (path, task)
+            })
+            .collect::<Vec<_>>();
+
+        let zettels = Self::join_parse_tasks(link_tasks, &mut diagnostics).await;
+
+        // zettels that were removed or edited might have been the last ones using a tag
+        TagRegistry::new(&ws).remove_orphans().await?;
+
+        diagnostics.extend(zettels.iter().flat_map(Diagnostic::for_zettel));
+        diagnostics.sort();
         let mut graph: ZkGraph = ZkGraph::from(&StableGraph::with_capacity(
             zettels.len(),
             zettels.len() * 3,
@@ -141,22 +350,36 @@ impl Kasten {
             zid_to_gid.insert(zettel.id.clone(), id);
         }
 
+        let mut backlink_index = BacklinkIndex::default();
         for zettel in &zettels {
             let src = zid_to_gid.get(&zettel.id).expect("must exist");
             for link in &zettel.links {
-                let dst = zid_to_gid.get(&link.dest).expect("must exist");
-                graph.add_edge(*src, *dst, link.clone());
+                // the file exists, but it couldn't be parsed into a zettel
+                let Some(dst) = zid_to_gid.get(&link.dest) else {
+                    warn!(
+                        "link target {} isn't part of the graph, skipping",
+                        link.dest
+                    );
+                    continue;
+                };
+                graph.add_edge_custom(*src, *dst, link.clone(), |edge| {
+                    link.apply_edge_transform(edge)
+                });
             }
+            backlink_index.update(zettel);
         }
 
         info!("graph: {graph:#?}");
 
         let kasten = Kasten {
-            id: ZettelId::default(),
-            name: Self::name_from_path_buf(root),
+            id: metadata.id,
+            name: metadata.name,
+            created_at: metadata.created_at,
             graph,
             ws,
             zid_to_gid,
+            backlink_index,
+            diagnostics,
             most_recently_edited: None,
         };
 
@@ -167,6 +390,803 @@ impl Kasten {
         Ok(kasten)
     }
 
+    /// Awaits the tasks parsing the file at each path, turning every failed one into a diagnostic.
+    async fn join_parse_tasks<T>(
+        tasks: Vec<(PathBuf, JoinHandle<ZkResult<T>>)>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Vec<T> {
+        let (paths, tasks): (Vec<_>, Vec<_>) = tasks.into_iter().unzip();
+
+        futures::future::join_all(tasks)
+            .await
+            .into_iter()
+            .zip(paths)
+            .filter_map(|(result, path)| {
+                let error = match result {
+                    Ok(Ok(parsed)) => return Some(parsed),
+                    Ok(Err(e)) => Diagnostic::from_error(&path, &e),
+                    Err(e) => Diagnostic::new(&path, 1, 1, Severity::Error, e.to_string()),
+                };
+                warn!("unable to parse zettel: {error}");
+                diagnostics.push(error);
+                None
+            })
+            .collect()
+    }
+
+    /// Replaces the diagnostics for the file at `path` with `diagnostics`.
+    fn replace_diagnostics(&mut self, path: &Path, diagnostics: Vec<Diagnostic>) {
+        let path = self.ws.relative_path(path);
+        self.diagnostics
+            .retain(|d| self.ws.relative_path(&d.path) != path);
+        self.diagnostics.extend(diagnostics);
+        self.diagnostics.sort();
+    }
+
+    /// Puts `zettel` into the graph, replacing the node and outgoing edges it had before, and
+    /// updates the backlinks and diagnostics coming from it.
+    fn upsert_zettel(&mut self, zettel: Zettel) -> NodeIndex {
+        let gid = match self.zid_to_gid.get(&zettel.id) {
+            Some(gid) => *gid,
+            None => {
+                info!("Zettel created while watch open!: {:#?}", zettel.id);
+                let gid = self
+                    .graph
+                    .add_node_custom(zettel.clone(), |node| zettel.apply_node_transform(node));
+
+                self.zid_to_gid.insert(zettel.id.clone(), gid);
+                gid
+            }
+        };
+
+        let curr_edgs = self
+            .graph
+            .g()
+            .edges(gid)
+            .map(|e| e.weight().id())
+            .collect::<Vec<_>>();
+
+        for edge in curr_edgs {
+            let _ = self.graph.remove_edge(edge);
+        }
+
+        for link in &zettel.links {
+            let Some(dest) = self.zid_to_gid.get(&link.dest).copied() else {
+                warn!(
+                    "link target {} isn't part of the graph, skipping",
+                    link.dest
+                );
+                continue;
+            };
+            self.graph.add_edge_custom(gid, dest, link.clone(), |edge| {
+                link.apply_edge_transform(edge)
+            });
+        }
+
+        self.backlink_index.update(&zettel);
+        let old_path = self.graph.node(gid).map(|node| node.payload().path.clone());
+        if let Some(old_path) = old_path.filter(|old_path| *old_path != zettel.path) {
+            self.replace_diagnostics(&old_path, vec![]);
+        }
+        let path = zettel.path.clone();
+        self.replace_diagnostics(&path, Diagnostic::for_zettel(&zettel));
+
+        let node = self.graph.node_mut(gid).expect("must exist");
+        zettel.apply_node_transform(node);
+        *node.payload_mut() = zettel;
+
+        gid
+    }
+
+    /// Removes the zettel with the given `id`, which lived at `path`, from the graph and returns
+    /// the paths of the other zettels linking to it, which have to be relinked.
+    fn remove_node(&mut self, id: &ZettelId, path: &Path) -> Vec<PathBuf> {
+        let sources = self
+            .backlinks(id)
+            .iter()
+            .filter(|backlink| backlink.source != *id)
+            .filter_map(|backlink| self.zettel(&backlink.source))
+            .map(|zettel| zettel.path.clone())
+            .collect::<BTreeSet<_>>();
+
+        if let Some(gid) = self.zid_to_gid.remove(id) {
+            let _ = self.graph.remove_node(gid);
+            if self.most_recently_edited == Some(gid) {
+                self.most_recently_edited = None;
+            }
+        }
+        self.backlink_index.remove_source(id);
+        self.replace_diagnostics(path, vec![]);
+
+        sources.into_iter().collect()
+    }
+
+    /// Re-parses the zettels at `paths` and resolves their links again, returning their ids.
+    async fn relink(&mut self, paths: Vec<PathBuf>) -> ZkResult<Vec<ZettelId>> {
+        let mut ids = Vec::with_capacity(paths.len());
+        for path in paths {
+            let zettel = Zettel::from_path(&path, &self.ws).await?;
+            ids.push(zettel.id.clone());
+            self.upsert_zettel(zettel);
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Returns the folder deleted zettels are moved to, inside of `.emergence`.
+    pub fn trash_dir(&self) -> PathBuf {
+        self.ws.root.join(".emergence").join(TRASH_DIR_NAME)
+    }
+
+    /// Deletes the zettel with the given `id` by moving its file into the [trash](Self::trash_dir),
+    /// at the same path relative to it as it had relative to the root, and removing it from the
+    /// db and the graph.
+    ///
+    /// Returns the ids of the zettels that linked to it, whose links are dangling now.
+    ///
+    /// # Errors
+    /// Errors if there is no such zettel or if the file can't be moved.
+    pub async fn delete_zettel(&mut self, id: &ZettelId) -> ZkResult<Vec<ZettelId>> {
+        let path = self
+            .zettel(id)
+            .map(|zettel| zettel.path.clone())
+            .ok_or_else(|| ZkError::ZettelNotFound(id.clone()))?;
+
+        let trashed = self.trash_dir().join(self.ws.relative_path(&path));
+        if let Some(dir) = trashed.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::rename(&path, &trashed)?;
+
+        Zettel::remove_from_db(id, &self.ws).await?;
+        TagRegistry::new(&self.ws).remove_orphans().await?;
+        let sources = self.remove_node(id, &path);
+
+        self.relink(sources).await
+    }
+
+    /// Moves the zettel with the given `id` out of the [trash](Self::trash_dir), back to where it
+    /// was deleted from, and adds it to the kasten again.
+    ///
+    /// Returns the ids of the zettels whose links point at it again.
+    ///
+    /// # Errors
+    /// Errors if the zettel isn't inside the trash, if a zettel with the same id or path exists
+    /// or if the file can't be moved.
+    pub async fn restore_zettel(&mut self, id: &ZettelId) -> ZkResult<Vec<ZettelId>> {
+        let trash_dir = self.trash_dir();
+        let trashed = WalkDir::new(&trash_dir)
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path())
+            .find(|path| ZettelId::try_from(path.as_path()).is_ok_and(|trashed| trashed == *id))
+            .ok_or_else(|| ZkError::ZettelNotFound(id.clone()))?;
+
+        let path = self.ws.root.join(
+            trashed
+                .strip_prefix(&trash_dir)
+                .expect("walked inside of the trash"),
+        );
+        if self.zid_to_gid.contains_key(id) || path.exists() {
+            return Err(ZkError::InvalidOperation(format!(
+                "can't restore {id}, {} already exists",
+                path.display()
+            )));
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::rename(&trashed, &path)?;
+
+        let zettel = Zettel::from_path(&path, &self.ws).await?;
+        self.upsert_zettel(zettel);
+
+        // any dangling link might have been pointing at it
+        let sources = self
+            .dangling_links()
+            .filter(|link| link.source != *id)
+            .filter_map(|link| self.zettel(&link.source))
+            .map(|zettel| zettel.path.clone())
+            .collect::<BTreeSet<_>>();
+        self.relink(sources.into_iter().collect()).await?;
+
+        let mut repaired = self
+            .backlinks(id)
+            .iter()
+            .map(|backlink| backlink.source.clone())
+            .filter(|source| source != id)
+            .collect::<Vec<_>>();
+        repaired.sort();
+        repaired.dedup();
+        Ok(repaired)
+    }
+
+    /// Merges the zettel `absorb` into the zettel `keep`: its content is appended to the one of
+    /// `keep`, its tags are added to those of `keep` and every link pointing at it is rewritten
+    /// to point at `keep`. `absorb` is then deleted like through [`Kasten::delete_zettel`].
+    ///
+    /// Returns the path of every file that was written, sorted.
+    ///
+    /// # Errors
+    /// Errors if either zettel doesn't exist, if they are the same or if any file can't be
+    /// written or moved.
+    pub async fn merge_zettels(
+        &mut self,
+        keep: &ZettelId,
+        absorb: &ZettelId,
+    ) -> ZkResult<Vec<PathBuf>> {
+        if keep == absorb {
+            return Err(ZkError::InvalidOperation(format!(
+                "can't merge {keep} into itself"
+            )));
+        }
+
+        let mut kept = self
+            .zettel(keep)
+            .cloned()
+            .ok_or_else(|| ZkError::ZettelNotFound(keep.clone()))?;
+        let mut absorbed = self
+            .zettel(absorb)
+            .cloned()
+            .ok_or_else(|| ZkError::ZettelNotFound(absorb.clone()))?;
+
+        // the edits have to be found while the db still knows about the absorbed zettel
+        let mut sources = self
+            .backlinks(absorb)
+            .iter()
+            .map(|backlink| backlink.source.clone())
+            .filter(|source| source != keep && source != absorb)
+            .collect::<Vec<_>>();
+        sources.sort();
+        sources.dedup();
+
+        let mut rewritten = vec![];
+        for source in &sources {
+            let Some(mut zettel) = self.zettel(source).cloned() else {
+                continue;
+            };
+            let edits = zettel.redirect_edits(&absorbed, &kept, &self.ws).await?;
+            if !edits.is_empty() {
+                zettel.content = apply_edits(&zettel.content, edits);
+                rewritten.push(zettel);
+            }
+        }
+
+        let edits = kept.redirect_edits(&absorbed, &kept, &self.ws).await?;
+        kept.content = apply_edits(&kept.content, edits);
+
+        let edits = absorbed.redirect_edits(&absorbed, &kept, &self.ws).await?;
+        absorbed.content = apply_edits(&absorbed.content, edits);
+
+        kept.content = format!(
+            "{}\n\n{}",
+            kept.content.trim_end(),
+            absorbed.content.trim_start_matches('\n')
+        );
+        for tag in absorbed.front_matter.tag_strings {
+            if !kept.front_matter.tag_strings.contains(&tag) {
+                kept.front_matter.tag_strings.push(tag);
+            }
+        }
+
+        kept.flush()?;
+        for zettel in &rewritten {
+            zettel.flush()?;
+        }
+
+        // relinks everything that linked to the absorbed zettel, which points at `keep` by now
+        self.delete_zettel(absorb).await?;
+
+        let mut touched = vec![kept.path];
+        touched.extend(rewritten.into_iter().map(|zettel| zettel.path));
+        self.relink(touched.clone()).await?;
+
+        touched.sort();
+        Ok(touched)
+    }
+
+    /// Splits the parts `splits` cover out of the zettel with the given `id`, each becoming a new
+    /// zettel next to it with the same tags. A piece starting with a heading is titled after it.
+    ///
+    /// Each piece is replaced by a link to the new zettel, and links with an anchor into a piece
+    /// are retargeted to it.
+    ///
+    /// Returns the ids of the new zettels, in the order they appeared in.
+    ///
+    /// # Errors
+    /// Errors if there is no such zettel, if the splits are invalid or if any file can't be
+    /// written.
+    pub async fn split_zettel(
+        &mut self,
+        id: &ZettelId,
+        splits: &[Split],
+    ) -> ZkResult<Vec<ZettelId>> {
+        let mut original = self
+            .zettel(id)
+            .cloned()
+            .ok_or_else(|| ZkError::ZettelNotFound(id.clone()))?;
+        let ranges = original.split_ranges(splits)?;
+        let headings = original.headings();
+
+        // the ranges cover content lines, the ones of headings and links are relative to the file
+        let content_line = |line: usize| line - 1 - original.content_line_offset;
+        // which piece a 1-based file line ends up in, `None` if it stays in the original
+        let piece_of = |line: usize| {
+            ranges
+                .iter()
+                .position(|range| range.contains(&content_line(line)))
+        };
+        // `None` if the anchor doesn't match any heading
+        let piece_of_anchor = |anchor: &str| {
+            headings
+                .iter()
+                .find(|heading| heading.matches(anchor))
+                .map(|heading| piece_of(heading.line))
+        };
+
+        let dir = self
+            .ws
+            .relative_path(original.path.parent().unwrap_or(&self.ws.root));
+        let mut pieces = vec![];
+        for (n, range) in ranges.iter().enumerate() {
+            let title = headings
+                .iter()
+                .find(|heading| content_line(heading.line) == range.start)
+                .map(|heading| heading.text.clone())
+                .unwrap_or_else(|| format!("{} ({})", original.front_matter.title, n + 1));
+
+            let mut builder = ZettelBuilder::new(&self.ws)
+                .with_title(title)
+                .with_directory(&dir);
+            for tag in &original.tags {
+                builder = builder.with_additional_tag(tag.clone());
+            }
+            // the content is filled in once the links inside of it are retargeted
+            pieces.push(builder.build().await?);
+        }
+
+        let mut edits = vec![];
+        for span in original.link_spans_to(id, &self.ws).await? {
+            let Some(to) = span.anchor.as_deref().and_then(piece_of_anchor) else {
+                continue;
+            };
+
+            // anchors without a path only have to change if they end up in another file
+            let only_anchor = !span.is_wikilink && span.target.is_none();
+            let stays = if only_anchor {
+                piece_of(span.line) == to
+            } else {
+                to.is_none()
+            };
+            if stays {
+                continue;
+            }
+
+            let to = to.map_or(&original, |piece| &pieces[piece]);
+            edits.extend(original.retarget_edit(&span, to, &self.ws));
+        }
+
+        // the edits don't touch any line breaks, so the ranges are still valid
+        let content = apply_edits(&original.content, edits);
+        let lines = content.lines().collect::<Vec<_>>();
+
+        let mut remaining = vec![];
+        let mut next = 0;
+        for (piece, range) in pieces.iter_mut().zip(&ranges) {
+            piece.content = format!("\n{}\n", lines[range.clone()].join("\n"));
+            piece.flush()?;
+
+            remaining.extend(lines[next..range.start].iter().map(|line| line.to_string()));
+            remaining.push(format!("[{}]({}.md)", piece.front_matter.title, piece.id));
+            next = range.end;
+        }
+        remaining.extend(lines[next..].iter().map(|line| line.to_string()));
+
+        original.content = remaining.join("\n");
+        original.flush()?;
+
+        // links from elsewhere into the pieces
+        let mut sources = self
+            .backlinks(id)
+            .iter()
+            .map(|backlink| backlink.source.clone())
+            .filter(|source| source != id)
+            .collect::<Vec<_>>();
+        sources.sort();
+        sources.dedup();
+
+        let mut rewritten = vec![];
+        for source in &sources {
+            let Some(mut zettel) = self.zettel(source).cloned() else {
+                continue;
+            };
+
+            let mut edits = vec![];
+            for span in zettel.link_spans_to(id, &self.ws).await? {
+                if let Some(piece) = span.anchor.as_deref().and_then(piece_of_anchor).flatten() {
+                    edits.extend(zettel.retarget_edit(&span, &pieces[piece], &self.ws));
+                }
+            }
+
+            if !edits.is_empty() {
+                zettel.content = apply_edits(&zettel.content, edits);
+                zettel.flush()?;
+                rewritten.push(zettel.path);
+            }
+        }
+
+        // the pieces have to be in the graph before anything linking to them
+        let mut paths = pieces
+            .iter()
+            .map(|piece| piece.path.clone())
+            .collect::<Vec<_>>();
+        paths.push(original.path);
+        paths.extend(rewritten);
+        self.relink(paths).await?;
+
+        Ok(pieces.into_iter().map(|piece| piece.id).collect())
+    }
+
+    /// Returns the zettel with the given `id`, if it's part of the graph.
+    pub fn zettel(&self, id: &ZettelId) -> Option<&Zettel> {
+        let gid = self.zid_to_gid.get(id)?;
+        self.graph.node(*gid).map(|node| node.payload())
+    }
+
+    /// Changes the title of the zettel with the given `id` to `new_title` and rewrites every link
+    /// that refers to it by its old title, be it as wikilink target or as link text.
+    ///
+    /// Returns the path of every file that was written, sorted.
+    ///
+    /// # Errors
+    /// Errors if there is no such zettel, if `new_title` can't be used as a title or if any file
+    /// can't be written.
+    pub async fn rename_zettel(
+        &mut self,
+        id: &ZettelId,
+        new_title: &str,
+    ) -> ZkResult<Vec<PathBuf>> {
+        let new_title = new_title.trim();
+        if new_title.is_empty() {
+            return Err(ZkError::InvalidOperation(
+                "titles can't be empty".to_owned(),
+            ));
+        }
+        // these would break the wikilinks we rewrite
+        if let Some(c) = new_title
+            .chars()
+            .find(|c| matches!(c, '[' | ']' | '|' | '#' | '\n' | '\r'))
+        {
+            return Err(ZkError::InvalidOperation(format!(
+                "titles can't contain {c:?}"
+            )));
+        }
+
+        let mut renamed = self
+            .zettel(id)
+            .cloned()
+            .ok_or_else(|| ZkError::ZettelNotFound(id.clone()))?;
+        let old_title = renamed.front_matter.title.clone();
+
+        // every zettel that might refer to this one, including itself
+        let mut sources = self
+            .backlinks(id)
+            .iter()
+            .map(|backlink| backlink.source.clone())
+            .filter(|source| source != id)
+            .collect::<Vec<_>>();
+        sources.sort();
+        sources.dedup();
+
+        // the edits have to be found while the db still has the old title
+        let mut rewritten = vec![];
+        for source in &sources {
+            let Some(mut zettel) = self.zettel(source).cloned() else {
+                continue;
+            };
+            let edits = zettel
+                .retitle_edits(id, &old_title, new_title, &self.ws)
+                .await?;
+            if !edits.is_empty() {
+                zettel.content = apply_edits(&zettel.content, edits);
+                rewritten.push(zettel);
+            }
+        }
+
+        let edits = renamed
+            .retitle_edits(id, &old_title, new_title, &self.ws)
+            .await?;
+        renamed.content = apply_edits(&renamed.content, edits);
+        renamed.front_matter.title = new_title.to_owned();
+
+        renamed.flush()?;
+        for zettel in &rewritten {
+            zettel.flush()?;
+        }
+
+        // the renamed zettel has to be synced first, so title based links resolve to it again
+        let mut touched = vec![renamed.path.clone()];
+        let zettel = Zettel::from_path(&renamed.path, &self.ws).await?;
+        self.upsert_zettel(zettel);
+
+        for zettel in rewritten {
+            let zettel = Zettel::from_path(&zettel.path, &self.ws).await?;
+            touched.push(zettel.path.clone());
+            self.upsert_zettel(zettel);
+        }
+
+        // links that dangled because nothing had the new title might resolve now
+        let dangling_sources = self
+            .dangling_links()
+            .filter(|link| {
+                let target = link
+                    .target
+                    .split_once("::")
+                    .map_or(link.target.as_str(), |(_, target)| target);
+                target.split('#').next().unwrap_or_default().trim() == new_title
+            })
+            .filter_map(|link| self.zettel(&link.source))
+            .map(|zettel| zettel.path.clone())
+            .collect::<HashSet<_>>();
+        for path in dangling_sources {
+            let zettel = Zettel::from_path(&path, &self.ws).await?;
+            self.upsert_zettel(zettel);
+        }
+
+        touched.sort();
+        Ok(touched)
+    }
+
+    /// Renames the tag `old` to `new` in the front matter and the inline `#tags` of every zettel
+    /// using it, along with every tag below it, so `project/lsp` becomes `work/lsp` when renaming `project` to
+    /// `work`. If `new` exists already, `old` is merged into it.
+    ///
+    /// Returns the path of every file that was written, sorted.
+    ///
+    /// # Errors
+    /// Errors if `old` doesn't exist, if `new` can't be used as a tag name or lies below `old`,
+    /// or if any file can't be written.
+    pub async fn rename_tag(&mut self, old: &str, new: &str) -> ZkResult<Vec<PathBuf>> {
+        let old = Tag::normalize(old);
+        let new = Tag::parse_name(new)
+            .map_err(|_| ZkError::InvalidOperation(format!("{new:?} isn't a valid tag name")))?;
+        if is_within(&new, &old) {
+            return Err(ZkError::InvalidOperation(format!(
+                "can't rename {old:?} to {new:?}, which lies below it"
+            )));
+        }
+
+        let registry = TagRegistry::new(&self.ws);
+        if registry.get(&old).await?.is_none() {
+            return Err(ZkError::InvalidOperation(format!(
+                "there is no tag {old:?}"
+            )));
+        }
+        registry.rename_rows(&old, &new).await?;
+
+        let rename = |tag: &str| format!("{new}{}", &tag[old.len()..]);
+
+        let mut touched = vec![];
+        for zettel in self.graph.nodes_iter().map(|(_, node)| node.payload()) {
+            // front matter tags are normalised already
+            let tags = &zettel.front_matter.tag_strings;
+            let edits = zettel
+                .inline_tags()
+                .into_iter()
+                .filter(|tag| is_within(&tag.name, &old))
+                .map(|tag| (tag.range, format!("#{}", rename(&tag.name))))
+                .collect::<Vec<_>>();
+            if edits.is_empty() && !tags.iter().any(|tag| is_within(tag, &old)) {
+                continue;
+            }
+
+            let mut renamed: Vec<String> = vec![];
+            for tag in tags {
+                let tag = if is_within(tag, &old) {
+                    rename(tag)
+                } else {
+                    tag.clone()
+                };
+                if !renamed.contains(&tag) {
+                    renamed.push(tag);
+                }
+            }
+
+            let mut zettel = zettel.clone();
+            zettel.front_matter.tag_strings = renamed;
+            zettel.content = apply_edits(&zettel.content, edits);
+            zettel.flush()?;
+            touched.push(zettel.path);
+        }
+
+        self.relink(touched.clone()).await?;
+        TagRegistry::new(&self.ws).remove_orphans().await?;
+
+        touched.sort();
+        Ok(touched)
+    }
+
+    /// Returns every link pointing at the zettel with the given `id`.
+    pub fn backlinks(&self, id: &ZettelId) -> &[Backlink] {
+        self.backlink_index.get(id)
+    }
+
+    /// Returns every link inside the kasten that couldn't be resolved to a zettel.
+    pub fn dangling_links(&self) -> impl Iterator<Item = &DanglingLink> {
+        self.graph
+            .nodes_iter()
+            .flat_map(|(_, node)| node.payload().dangling_links.iter())
+    }
+
+    /// Returns every link inside the kasten that points outside of it.
+    pub fn external_links(&self) -> impl Iterator<Item = &ExternalLink> {
+        self.graph
+            .nodes_iter()
+            .flat_map(|(_, node)| node.payload().external_links.iter())
+    }
+
+    /// Searches the title and content of every zettel in the kasten, best matches first.
+    pub async fn search(&self, query: &str) -> ZkResult<Vec<SearchHit>> {
+        SearchHit::query(query, &self.ws).await
+    }
+
+    /// Returns the ids of every zettel whose custom properties match `filter`.
+    pub async fn filter(&self, filter: &PropertyFilter) -> ZkResult<Vec<ZettelId>> {
+        filter.matching(&self.ws).await
+    }
+
+    /// Returns the ids of every zettel matching `query`, see [`Query`] for how it is written.
+    pub async fn query(&self, query: &str) -> ZkResult<Vec<ZettelId>> {
+        query.parse::<Query>()?.matching(self).await
+    }
+
+    /// Returns the ids of every zettel tagged with `tag` or any tag below it.
+    pub async fn tagged(&self, tag: &str) -> ZkResult<Vec<ZettelId>> {
+        Tag::zettels(tag, &self.ws).await
+    }
+
+    /// Returns the tags of the kasten arranged by hierarchy.
+    pub async fn tag_tree(&self) -> ZkResult<Vec<TagNode>> {
+        Tag::tree(&self.ws).await
+    }
+
+    /// Returns every link between zettels in the graph that expresses `relation`.
+    pub fn links_with_relation<'a>(
+        &'a self,
+        relation: &'a Relation,
+    ) -> impl Iterator<Item = &'a Link> {
+        self.graph
+            .edges_iter()
+            .map(|(_, edge)| edge.payload())
+            .filter(move |link| link.relation.as_ref() == Some(relation))
+    }
+
+    /// Analyses how the zettels of the graph are linked together, see [`GraphAnalytics`].
+    pub fn analytics(&self) -> GraphAnalytics {
+        GraphAnalytics::of(&self.graph)
+    }
+
+    /// Re-parses the zettel at `path` for [`Kasten::watch`], after it was moved there.
+    async fn watch_upsert(k_handle: &KastenHandle, path: &Path) {
+        let ws = k_handle
+            .lock()
+            .expect("lock must not be poisoned")
+            .ws
+            .clone();
+
+        let zettel = match Zettel::from_path(path, &ws).await {
+            Ok(zettel) => zettel,
+            Err(e) => {
+                error!("Unable to parse zettel from path: {path:#?}, error: {e:#?}");
+                return;
+            }
+        };
+
+        // path links to the zettel point at where it was before
+        let sources = {
+            let mut kasten_guard = k_handle.lock().expect("lock must not be poisoned");
+            let moved = kasten_guard
+                .zettel(&zettel.id)
+                .is_some_and(|old| old.path != zettel.path);
+            let sources = kasten_guard
+                .backlinks(&zettel.id)
+                .iter()
+                .filter(|backlink| moved && backlink.source != zettel.id)
+                .filter_map(|backlink| kasten_guard.zettel(&backlink.source))
+                .map(|source| source.path.clone())
+                .collect::<BTreeSet<_>>();
+
+            kasten_guard.upsert_zettel(zettel);
+            sources
+        };
+
+        for source in sources {
+            match Zettel::from_path(&source, &ws).await {
+                Ok(z) => {
+                    k_handle
+                        .lock()
+                        .expect("lock must not be poisoned")
+                        .upsert_zettel(z);
+                }
+                Err(e) => error!("Unable to relink zettel at {source:#?}, error: {e:#?}"),
+            }
+        }
+    }
+
+    /// Handles the old `path` of a rename for [`Kasten::watch`]. Zettels that lived there, or
+    /// below it for a directory, are re-parsed where they are now, or removed if they were
+    /// moved out of the kasten.
+    async fn watch_moved_away(k_handle: &KastenHandle, path: &Path) {
+        let (ws, moved) = {
+            let kasten_guard = k_handle.lock().expect("lock must not be poisoned");
+            let ws = kasten_guard.ws.clone();
+            let old = ws.relative_path(path);
+            let moved = kasten_guard
+                .graph
+                .nodes_iter()
+                .map(|(_, node)| node.payload())
+                .filter(|zettel| ws.relative_path(&zettel.path).starts_with(&old))
+                .map(|zettel| (zettel.id.clone(), zettel.path.clone()))
+                .collect::<Vec<_>>();
+            (ws, moved)
+        };
+        if moved.is_empty() {
+            return;
+        }
+
+        // the rename already happened, so the new path can be found on disk
+        let files = zettel_files(&ws)
+            .filter_map(|file| Some((ZettelId::try_from(file.as_path()).ok()?, file)))
+            .collect::<HashMap<_, _>>();
+
+        for (id, old_path) in moved {
+            match files.get(&id) {
+                Some(new_path) => Self::watch_upsert(k_handle, new_path).await,
+                None => Self::watch_remove(k_handle, &id, &old_path).await,
+            }
+        }
+    }
+
+    /// Removes the zettel with the given `id`, whose file at `path` is gone, for
+    /// [`Kasten::watch`].
+    async fn watch_remove(k_handle: &KastenHandle, id: &ZettelId, path: &Path) {
+        info!("deleting zettel: {id:#?}");
+
+        let ws = k_handle
+            .lock()
+            .expect("lock must not be poisoned")
+            .ws
+            .clone();
+
+        if let Err(e) = Zettel::remove_from_db(id, &ws).await {
+            error!("Unable to remove zettel from db: {e:#?}");
+        }
+
+        if let Err(e) = TagRegistry::new(&ws).remove_orphans().await {
+            error!("Unable to remove unused tags: {e:#?}");
+        }
+
+        let sources = k_handle
+            .lock()
+            .expect("lock must not be poisoned")
+            .remove_node(id, path);
+
+        // the links to the removed zettel are dangling now
+        for source in sources {
+            match Zettel::from_path(&source, &ws).await {
+                Ok(z) => {
+                    k_handle
+                        .lock()
+                        .expect("lock must not be poisoned")
+                        .upsert_zettel(z);
+                }
+                Err(e) => error!("Unable to relink zettel at {source:#?}, error: {e:#?}"),
+            }
+        }
+    }
+
     /// NOTE: This function will block forever
     /// Will watch the underlying folder and apply any file changes to the `ZKGraph` of this `Kasten`
     pub async fn watch(k_handle: KastenHandle) -> ZkResult<()> {
@@ -194,109 +1214,85 @@ impl Kasten {
 
                     match event.kind {
                         // a file was deleted
-                        EventKind::Remove(RemoveKind::Any | RemoveKind::File)
-                        | EventKind::Modify(ModifyKind::Name(_)) => {
+                        EventKind::Remove(RemoveKind::Any | RemoveKind::File) => {
                             // this is the path that was removed
                             for path in event.paths {
-                                let id = {
-                                    // this will be true if the path really doesn't exist -> meaning it got deleted
-                                    if path.canonicalize().is_err() {
-                                        match ZettelId::try_from(path) {
-                                            Ok(id) => id,
-                                            Err(_) => continue,
-                                        }
-                                    } else {
-                                        // file still exists
-                                        continue;
-                                    }
-                                };
-
-                                info!("deleting zettel: {id:#?}");
-
-                                let mut kasten_guard =
-                                    k_handle.lock().expect("lock must not be poisoned");
-
-                                let Some(g_id) = kasten_guard.zid_to_gid.get(&id).copied() else {
-                                    warn!(
-                                        "the id we were trying to delete didnt exist inside zid_to_gid, skipping"
-                                    );
-
+                                // this will be true if the path really doesn't exist -> meaning it got deleted
+                                if path.canonicalize().is_ok() {
+                                    continue;
+                                }
+                                let Ok(id) = ZettelId::try_from(path.as_path()) else {
                                     continue;
                                 };
 
-                                // remove from graph
-                                let _ = kasten_guard.graph.remove_node(g_id);
-                                kasten_guard.zid_to_gid.remove(&id);
+                                Self::watch_remove(&k_handle, &id, &path).await;
+                            }
+                        }
+                        // a file or directory was renamed or moved, the event carries the old
+                        // path, the new one or both of them depending on the platform
+                        EventKind::Modify(ModifyKind::Name(_)) => {
+                            for path in event.paths {
+                                if path.is_dir() {
+                                    let dir = ws.relative_path(&path);
+                                    for path in zettel_files(&ws)
+                                        .filter(|file| ws.relative_path(file).starts_with(&dir))
+                                        .collect::<Vec<_>>()
+                                    {
+                                        Self::watch_upsert(&k_handle, &path).await;
+                                    }
+                                } else if path.exists() {
+                                    if is_part_of_kasten(&ws, &path) {
+                                        Self::watch_upsert(&k_handle, &path).await;
+                                    }
+                                } else {
+                                    Self::watch_moved_away(&k_handle, &path).await;
+                                }
                             }
                         }
                         EventKind::Modify(ModifyKind::Data(_)) => {
                             for path in event.paths {
-                                let Ok(z) = Zettel::from_path(&path, &ws).await.inspect_err(|e| {
-                                    error!(
-                                        "Unable to parse zettel from path: {path:#?}, error: {e:#?}"
-                                    )
-                                }) else {
+                                // same as when parsing, hidden and ignored directories aren't part
+                                // of the kasten
+                                let relative = ws.relative_path(&path);
+                                if relative.iter().any(is_hidden) || ws.config.is_ignored(&relative)
+                                {
                                     continue;
-                                };
-
-                                info!("Processing content change in zettel: {z:#?}");
+                                }
 
-                                let mut kasten_guard =
-                                    k_handle.lock().expect("lock must not be poisoned");
-
-                                let gid = {
-                                    match kasten_guard.zid_to_gid.get(&z.id) {
-                                        Some(gid) => *gid,
-                                        None => {
-                                            info!("Zettel created while watch open!: {:#?}", z.id);
-                                            // this zettel was created while we have watch open, lets just add
-                                            // it to kasten_guard.thegraph and the hashmap
-                                            let gid = kasten_guard
-                                                .graph
-                                                .add_node_custom(z.clone(), |node| {
-                                                    z.apply_node_transform(node)
-                                                });
-
-                                            kasten_guard.zid_to_gid.insert(z.id.clone(), gid);
-                                            gid
+                                let z = match Zettel::from_path(&path, &ws).await {
+                                    Ok(z) => z,
+                                    Err(e) => {
+                                        error!(
+                                            "Unable to parse zettel from path: {path:#?}, error: {e:#?}"
+                                        );
+                                        // non zettel files, like the db, aren't worth a diagnostic
+                                        if ZettelId::try_from(path.as_path()).is_ok() {
+                                            k_handle
+                                                .lock()
+                                                .expect("lock must not be poisoned")
+                                                .replace_diagnostics(
+                                                    &path,
+                                                    vec![Diagnostic::from_error(&path, &e)],
+                                                );
                                         }
+                                        continue;
                                     }
                                 };
 
-                                let curr_edgs = kasten_guard
-                                    .graph
-                                    .g()
-                                    .edges(gid)
-                                    .map(|e| e.weight().id())
-                                    .collect::<Vec<_>>();
+                                info!("Processing content change in zettel: {z:#?}");
 
-                                for edge in curr_edgs {
-                                    let _ = kasten_guard.graph.remove_edge(edge);
-                                }
+                                {
+                                    let mut kasten_guard =
+                                        k_handle.lock().expect("lock must not be poisoned");
 
-                                for link in z.links.clone() {
-                                    let dest = *kasten_guard
-                                        .zid_to_gid
-                                        .get(&link.dest)
-                                        .expect("must exist");
-                                    kasten_guard.graph.add_edge(gid, dest, link);
+                                    let gid = kasten_guard.upsert_zettel(z);
+
+                                    kasten_guard.most_recently_edited = Some(gid)
                                 }
 
-                                // swap out the zettel with the one we parsed
-                                // let x = kasten_guard.graph.node_mut(gid).expect("must exist");
-                                // *x.payload_mut() = z;
-                                // let x = kasten_guard
-                                //     .graph
-                                //     .g_mut()
-                                //     .node_weight_mut(gid)
-                                //     .expect("must exist");
-                                // *x.payload_mut() = z;
-
-                                let node = kasten_guard.graph.node_mut(gid).expect("must exist");
-                                z.apply_node_transform(node);
-                                *node.payload_mut() = z;
-
-                                kasten_guard.most_recently_edited = Some(gid)
+                                if let Err(e) = TagRegistry::new(&ws).remove_orphans().await {
+                                    error!("Unable to remove unused tags: {e:#?}");
+                                }
                             }
                         }
 
@@ -1,18 +1,32 @@
 // #![warn(missing_debug_implementations, missing_docs)]
+mod analytics;
+mod color;
+mod config;
 mod db;
+mod diagnostic;
 mod error;
 mod id;
 mod kasten;
 mod link;
+mod metadata;
+mod query;
+mod search;
 mod tag;
 mod workspace;
 mod zettel;
 
+pub use analytics::*;
+pub use color::*;
+pub use config::*;
 pub use db::*;
+pub use diagnostic::*;
 pub use error::*;
 pub use id::*;
 pub use kasten::*;
 pub use link::*;
+pub use metadata::*;
+pub use query::*;
+pub use search::*;
 pub use tag::*;
 pub use workspace::*;
 pub use zettel::*;
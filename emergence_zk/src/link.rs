@@ -1,11 +1,32 @@
+use std::{collections::HashMap, fmt::Display};
+
+use egui_graphs::{Edge, default_edge_transform};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter};
 use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    Heading, Workspace, Zettel, ZettelId, ZkResult,
+    entities::{link, prelude::*},
+};
 
-use crate::ZettelId;
+/// Values of the `kind` column of the `link` table
+const INTERNAL: &str = "internal";
+const DANGLING: &str = "dangling";
+const EXTERNAL: &str = "external";
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Link {
     pub source: ZettelId,
     pub dest: ZettelId,
+    /// The part after the `#` in a link like `abc.md#method`
+    pub anchor: Option<String>,
+    /// 1-based line inside the file of the source zettel that this link sits on
+    pub line: usize,
+    /// The paragraph, heading or list item this link was found in
+    pub context: String,
+    /// What kind of relation this link expresses, like "supports" or "contradicts"
+    pub relation: Option<Relation>,
 }
 
 impl Link {
@@ -13,6 +34,364 @@ impl Link {
         Self {
             source: source.into(),
             dest: dest.into(),
+            anchor: None,
+            line: 0,
+            context: String::new(),
+            relation: None,
+        }
+    }
+
+    pub fn with_relation(mut self, relation: Option<Relation>) -> Self {
+        self.relation = relation;
+        self
+    }
+
+    pub fn with_anchor(mut self, anchor: Option<impl Into<String>>) -> Self {
+        self.anchor = anchor.map(Into::into);
+        self
+    }
+
+    pub fn with_position(mut self, line: usize, context: impl Into<String>) -> Self {
+        self.line = line;
+        self.context = context.into();
+        self
+    }
+
+    pub fn apply_edge_transform(&self, edge: &mut Edge<Zettel, Link>) {
+        default_edge_transform(edge);
+        if let Some(relation) = &self.relation {
+            edge.set_label(relation.to_string());
+        }
+    }
+
+    /// Returns the heading inside of `dest` this link's anchor points at, if any.
+    pub fn heading(&self, dest: &Zettel) -> Option<Heading> {
+        let anchor = self.anchor.as_deref()?;
+        dest.headings().into_iter().find(|h| h.matches(anchor))
+    }
+
+    /// Returns the links going out of the zettel with the given `id`, as stored in the db.
+    pub async fn outgoing(id: &ZettelId, ws: &Workspace) -> ZkResult<Vec<Self>> {
+        let links = LinkEntity::find()
+            .filter(link::Column::SourceNanoId.eq(id.as_str()))
+            .filter(link::Column::Kind.eq(INTERNAL))
+            .all(ws.db.as_ref())
+            .await?;
+
+        Ok(links.into_iter().map(Self::from).collect())
+    }
+
+    /// Returns the links pointing at the zettel with the given `id`, as stored in the db.
+    pub async fn incoming(id: &ZettelId, ws: &Workspace) -> ZkResult<Vec<Backlink>> {
+        let links = LinkEntity::find()
+            .filter(link::Column::DestNanoId.eq(id.as_str()))
+            .filter(link::Column::Kind.eq(INTERNAL))
+            .find_also_related(ZettelEntity)
+            .all(ws.db.as_ref())
+            .await?;
+
+        Ok(links
+            .into_iter()
+            .map(|(link, source)| Backlink {
+                source: link.source_nano_id.as_str().into(),
+                source_title: source.map(|z| z.title).unwrap_or_default(),
+                line: link.line as usize,
+                context: link.context,
+            })
+            .collect())
+    }
+}
+
+impl From<link::Model> for Link {
+    fn from(value: link::Model) -> Self {
+        Link {
+            source: value.source_nano_id.as_str().into(),
+            dest: value.dest_nano_id.as_str().into(),
+            anchor: value.anchor,
+            line: value.line as usize,
+            context: value.context,
+            relation: value.relation.as_deref().map(Relation::from),
+        }
+    }
+}
+
+/// Every link going out of a single zettel, as stored in the `link` table.
+///
+/// Dangling and external links are stored next to the resolved ones, with their target or url
+/// in place of the destination, so zettels that didn't change since the last parse can reuse
+/// them without being resolved again.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StoredLinks {
+    pub links: Vec<Link>,
+    pub dangling_links: Vec<DanglingLink>,
+    pub external_links: Vec<ExternalLink>,
+}
+
+impl StoredLinks {
+    /// Loads the stored links of every zettel in the db, keyed by the zettel they go out of.
+    pub async fn load_all(ws: &Workspace) -> ZkResult<HashMap<ZettelId, Self>> {
+        let mut stored: HashMap<ZettelId, Self> = HashMap::new();
+
+        for row in LinkEntity::find().all(ws.db.as_ref()).await? {
+            let source: ZettelId = row.source_nano_id.as_str().into();
+            let entry = stored.entry(source.clone()).or_default();
+
+            match row.kind.as_str() {
+                DANGLING => {
+                    let Some(reason) = row
+                        .reason
+                        .as_deref()
+                        .and_then(|reason| serde_json::from_str(reason).ok())
+                    else {
+                        warn!("dangling link from {source} has no readable reason, skipping");
+                        continue;
+                    };
+
+                    entry.dangling_links.push(DanglingLink {
+                        source,
+                        target: row.dest_nano_id,
+                        reason,
+                        line: row.line as usize,
+                        context: row.context,
+                    });
+                }
+                EXTERNAL => entry.external_links.push(ExternalLink {
+                    source,
+                    url: row.dest_nano_id,
+                    line: row.line as usize,
+                    context: row.context,
+                }),
+                _ => entry.links.push(Link::from(row)),
+            }
+        }
+
+        Ok(stored)
+    }
+
+    /// Replaces the links stored in the db for `zettel` with the ones it currently has.
+    pub async fn store(zettel: &Zettel, ws: &Workspace) -> ZkResult<()> {
+        LinkEntity::delete_many()
+            .filter(link::Column::SourceNanoId.eq(zettel.id.as_str()))
+            .exec(ws.db.as_ref())
+            .await?;
+
+        let links = zettel.links.iter().map(|link| link::ActiveModel {
+            source_nano_id: Set(link.source.to_string()),
+            dest_nano_id: Set(link.dest.to_string()),
+            anchor: Set(link.anchor.clone()),
+            line: Set(link.line as i32),
+            context: Set(link.context.clone()),
+            relation: Set(link.relation.as_ref().map(Relation::to_string)),
+            kind: Set(INTERNAL.to_owned()),
+            ..Default::default()
+        });
+
+        let dangling_links = zettel.dangling_links.iter().map(|link| link::ActiveModel {
+            source_nano_id: Set(link.source.to_string()),
+            dest_nano_id: Set(link.target.clone()),
+            line: Set(link.line as i32),
+            context: Set(link.context.clone()),
+            kind: Set(DANGLING.to_owned()),
+            reason: Set(serde_json::to_string(&link.reason).ok()),
+            ..Default::default()
+        });
+
+        let external_links = zettel.external_links.iter().map(|link| link::ActiveModel {
+            source_nano_id: Set(link.source.to_string()),
+            dest_nano_id: Set(link.url.clone()),
+            line: Set(link.line as i32),
+            context: Set(link.context.clone()),
+            kind: Set(EXTERNAL.to_owned()),
+            ..Default::default()
+        });
+
+        let rows = links
+            .chain(dangling_links)
+            .chain(external_links)
+            .collect::<Vec<_>>();
+
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        LinkEntity::insert_many(rows).exec(ws.db.as_ref()).await?;
+
+        Ok(())
+    }
+}
+
+/// The kind of relation a link expresses between two zettels.
+///
+/// Written as a prefix inside wikilinks, `[[supports::kdj6yav]]`, or as the title of a markdown
+/// link after `rel:`, `[x](kdj6yav.md "rel: supports")`. Other markdown link titles are plain
+/// tooltips.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Relation {
+    Supports,
+    Contradicts,
+    ExampleOf,
+    SeeAlso,
+    /// Any relation we don't have a name for, normalized to lowercase `kebab-case`
+    Other(String),
+}
+
+impl Relation {
+    /// Marks the title of a markdown link as the relation of the link
+    pub const TITLE_PREFIX: &str = "rel:";
+
+    /// The relation written in the `title` of a markdown link, if it starts with
+    /// [`Relation::TITLE_PREFIX`].
+    pub fn from_link_title(title: &str) -> Option<Self> {
+        let relation = title.trim_start().strip_prefix(Self::TITLE_PREFIX)?;
+        (!relation.trim().is_empty()).then(|| Self::from(relation))
+    }
+}
+
+impl From<&str> for Relation {
+    fn from(value: &str) -> Self {
+        let normalized = value
+            .trim()
+            .to_lowercase()
+            .split(|c: char| c.is_whitespace() || c == '_' || c == '-')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+
+        match normalized.as_str() {
+            "supports" => Relation::Supports,
+            "contradicts" => Relation::Contradicts,
+            "example-of" => Relation::ExampleOf,
+            "see-also" => Relation::SeeAlso,
+            _ => Relation::Other(normalized),
+        }
+    }
+}
+
+impl Display for Relation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Relation::Supports => write!(f, "supports"),
+            Relation::Contradicts => write!(f, "contradicts"),
+            Relation::ExampleOf => write!(f, "example-of"),
+            Relation::SeeAlso => write!(f, "see-also"),
+            Relation::Other(relation) => write!(f, "{relation}"),
+        }
+    }
+}
+
+/// A link to something inside the kasten that couldn't be resolved to a zettel, or to a heading
+/// inside of one.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DanglingLink {
+    pub source: ZettelId,
+    /// The destination as it was written in the source zettel
+    pub target: String,
+    pub reason: DanglingReason,
+    /// 1-based line inside the file of the source zettel that this link sits on
+    pub line: usize,
+    /// The paragraph, heading or list item this link was found in
+    pub context: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DanglingReason {
+    /// No zettel has the target as its id or title
+    NotFound,
+    /// More than one zettel has the target as its title
+    Ambiguous(Vec<ZettelId>),
+    /// The target zettel exists, but has no heading matching the anchor
+    UnknownAnchor(String),
+}
+
+impl Display for DanglingReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DanglingReason::NotFound => write!(f, "not found"),
+            DanglingReason::Ambiguous(candidates) => {
+                write!(f, "ambiguous between")?;
+                for candidate in candidates {
+                    write!(f, " {candidate}")?;
+                }
+                Ok(())
+            }
+            DanglingReason::UnknownAnchor(anchor) => write!(f, "no heading matching #{anchor}"),
         }
     }
 }
+
+/// A link to something outside of the kasten, like an `https://` url.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ExternalLink {
+    pub source: ZettelId,
+    pub url: String,
+    /// 1-based line inside the file of the source zettel that this link sits on
+    pub line: usize,
+    /// The paragraph, heading or list item this link was found in
+    pub context: String,
+}
+
+impl ExternalLink {
+    /// Returns whether `dest_url` points outside of the kasten, meaning it starts with a url
+    /// scheme like `https:` or `mailto:`.
+    pub fn is_external(dest_url: &str) -> bool {
+        let Some((scheme, _)) = dest_url.split_once(':') else {
+            return false;
+        };
+
+        // a single letter is more likely a windows drive than a scheme
+        scheme.len() > 1
+            && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    }
+}
+
+/// An incoming link, as seen from the zettel that is being linked to.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Backlink {
+    pub source: ZettelId,
+    pub source_title: String,
+    /// 1-based line inside the file of the source zettel that the link sits on
+    pub line: usize,
+    /// The paragraph, heading or list item the link was found in
+    pub context: String,
+}
+
+/// Maps every zettel to the links pointing at it.
+#[derive(Debug, Clone, Default)]
+pub struct BacklinkIndex {
+    inner: HashMap<ZettelId, Vec<Backlink>>,
+}
+
+impl BacklinkIndex {
+    /// Returns all the links pointing at `id`.
+    pub fn get(&self, id: &ZettelId) -> &[Backlink] {
+        self.inner.get(id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Replaces every backlink originating from `zettel` with the links it currently has.
+    pub fn update(&mut self, zettel: &Zettel) {
+        self.remove_source(&zettel.id);
+
+        for link in &zettel.links {
+            self.inner
+                .entry(link.dest.clone())
+                .or_default()
+                .push(Backlink {
+                    source: zettel.id.clone(),
+                    source_title: zettel.front_matter.title.clone(),
+                    line: link.line,
+                    context: link.context.clone(),
+                });
+        }
+    }
+
+    /// Removes every backlink originating from the zettel with `source` as its id.
+    pub fn remove_source(&mut self, source: &ZettelId) {
+        self.inner.retain(|_, backlinks| {
+            backlinks.retain(|backlink| backlink.source != *source);
+            !backlinks.is_empty()
+        });
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{ZettelId, ZkError, ZkResult};
+
+/// Name of the metadata file inside of the `.emergence` folder of a kasten
+pub const METADATA_FILE_NAME: &str = "kasten.toml";
+
+/// Version of the on-disk layout of a kasten this build reads and writes.
+///
+/// - `0`: only the `.emergence` folder and the db, without any metadata
+/// - `1`: metadata inside of `.emergence/kasten.toml`
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// What identifies a kasten across parses, stored in `.emergence/kasten.toml`.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct KastenMetadata {
+    /// Stable id, doesn't change when the kasten is moved or renamed
+    pub id: ZettelId,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    /// Layout version the kasten was last written with, see [`SCHEMA_VERSION`]
+    pub schema_version: u32,
+}
+
+impl KastenMetadata {
+    /// Returns fresh metadata for a kasten at `root`, named after its folder.
+    pub fn new(root: &Path) -> Self {
+        Self {
+            id: ZettelId::default(),
+            name: Self::name_from_root(root),
+            created_at: Local::now().naive_local(),
+            schema_version: SCHEMA_VERSION,
+        }
+    }
+
+    fn name_from_root(root: &Path) -> String {
+        root.canonicalize()
+            .unwrap_or_else(|_| root.to_path_buf())
+            .file_name()
+            .map(|os_str| os_str.to_string_lossy().into_owned())
+            .unwrap_or("ZettleKasten".to_owned())
+    }
+
+    /// Returns the path of the metadata file of the kasten at `root`.
+    pub fn path(root: &Path) -> PathBuf {
+        root.join(".emergence").join(METADATA_FILE_NAME)
+    }
+
+    /// Loads the metadata of the kasten at `root`, migrating kastens with an older layout to the
+    /// current one.
+    ///
+    /// # Errors
+    /// Errors if `root` isn't a kasten, if it was written by a newer version of emergence, or if
+    /// the metadata is unreadable.
+    pub fn load(root: &Path) -> ZkResult<Self> {
+        let emergence_folder = root.join(".emergence");
+        if !emergence_folder.is_dir() {
+            return Err(ZkError::LayoutError(format!(
+                "{} isn't a kasten, it has no .emergence folder",
+                root.display()
+            )));
+        }
+
+        let path = Self::path(root);
+        let metadata = match fs::read_to_string(&path) {
+            Ok(string) => toml::from_str::<Self>(&string)
+                .map_err(|e| ZkError::LayoutError(format!("{}: {e}", path.display())))?,
+            // kastens from before the metadata existed
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let created_at = fs::metadata(&emergence_folder)
+                    .and_then(|metadata| metadata.created().or_else(|_| metadata.modified()))
+                    .map(|time| DateTime::<Local>::from(time).naive_local())
+                    .unwrap_or_else(|_| Local::now().naive_local());
+
+                Self {
+                    created_at,
+                    schema_version: 0,
+                    ..Self::new(root)
+                }
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if metadata.schema_version > SCHEMA_VERSION {
+            return Err(ZkError::LayoutError(format!(
+                "{} has layout version {}, but this version of emergence only understands up to {SCHEMA_VERSION}",
+                root.display(),
+                metadata.schema_version,
+            )));
+        }
+
+        if metadata.schema_version < SCHEMA_VERSION {
+            return metadata.migrate(root);
+        }
+
+        Ok(metadata)
+    }
+
+    /// Brings a kasten with an older layout up to [`SCHEMA_VERSION`], one version at a time.
+    fn migrate(mut self, root: &Path) -> ZkResult<Self> {
+        while self.schema_version < SCHEMA_VERSION {
+            info!(
+                "migrating kasten {} from layout version {}",
+                root.display(),
+                self.schema_version
+            );
+
+            // going from 0 to 1 only needs the metadata to be written, which happens below
+            self.schema_version += 1;
+        }
+
+        self.store(root)?;
+        Ok(self)
+    }
+
+    /// Writes the metadata of the kasten at `root` to its metadata file.
+    pub fn store(&self, root: &Path) -> ZkResult<()> {
+        let string =
+            toml::to_string_pretty(self).map_err(|e| ZkError::LayoutError(e.to_string()))?;
+        fs::write(Self::path(root), string)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,506 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    iter::Peekable,
+    str::FromStr,
+    vec::IntoIter,
+};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{Kasten, PropertyFilter, Tag, ZettelId, ZkError, ZkResult, search};
+
+/// A query selecting zettels, written like
+/// `tag:rust AND -tag:draft AND links-to:abc AND created>2025-01-01 AND "borrow checker"`.
+///
+/// Terms next to each other have to match all of them, as if joined by `AND`. `OR` matches
+/// either side, `NOT` or a leading `-` inverts a term and parentheses group terms. `NOT` binds
+/// tighter than `AND`, which binds tighter than `OR`.
+///
+/// | Term                                  | Matches zettels                                   |
+/// |---------------------------------------|---------------------------------------------------|
+/// | `tag:rust`                            | tagged with `rust` or any tag below it            |
+/// | `links-to:abc`                        | linking to the zettel with id or title `abc`      |
+/// | `linked-from:abc`                     | the zettel with id or title `abc` links to        |
+/// | `has:links`, `has:backlinks`          | linking to or being linked to by any zettel       |
+/// | `created>2025-01-01`                  | created after that day, also `>=`, `<`, `<=`, `=` |
+/// | `title:borrow`                        | whose title contains `borrow`, ignoring case      |
+/// | `prop:status=draft`, `prop:source`    | whose properties match, see [`PropertyFilter`]    |
+/// | `borrow`, `"borrow checker"`          | containing the word or phrase in title or content |
+///
+/// Values containing spaces can be quoted, like `title:"borrow checker"`. Words that only look
+/// like a field, like `created`, `note:` or `http://example.com`, are searched for as text.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Query {
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    Term(Term),
+}
+
+/// A single condition of a [`Query`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Term {
+    /// Tagged with this tag or any tag below it
+    Tag(String),
+    /// Links to the zettel with this id or title
+    LinksTo(String),
+    /// Is linked to by the zettel with this id or title
+    LinkedFrom(String),
+    /// Links to any zettel
+    HasLinks,
+    /// Is linked to by any zettel
+    HasBacklinks,
+    /// The day the zettel was created on compares to the date like this
+    Created(Comparison, NaiveDate),
+    /// The title contains this, ignoring case
+    Title(String),
+    /// The custom properties match this filter
+    Property(PropertyFilter),
+    /// The title or content contains this word or phrase
+    Text(String),
+}
+
+/// How a value has to compare to the one inside of a [`Term`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    /// Whether a value that compares like `ordering` to the expected one satisfies this.
+    pub fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Comparison::Less => ordering.is_lt(),
+            Comparison::LessOrEqual => ordering.is_le(),
+            Comparison::Equal => ordering.is_eq(),
+            Comparison::GreaterOrEqual => ordering.is_ge(),
+            Comparison::Greater => ordering.is_gt(),
+        }
+    }
+}
+
+impl Query {
+    /// Returns the ids of every zettel inside of `kasten` that match this query, sorted.
+    ///
+    /// Tags, properties and full text are looked up in the db, everything else in the graph.
+    pub async fn matching(&self, kasten: &Kasten) -> ZkResult<Vec<ZettelId>> {
+        // the db is asked once per term up front, so evaluating needs no recursive futures
+        let mut looked_up = HashMap::new();
+        for term in self.terms() {
+            if looked_up.contains_key(term) {
+                continue;
+            }
+            let ids = match term {
+                Term::Tag(tag) => Tag::zettels(tag, &kasten.ws).await?,
+                Term::Property(filter) => filter.matching(&kasten.ws).await?,
+                Term::Text(text) => search::phrase_matches(text, &kasten.ws).await?,
+                _ => continue,
+            };
+            looked_up.insert(term, ids.into_iter().collect::<HashSet<_>>());
+        }
+
+        let all = kasten
+            .graph
+            .nodes_iter()
+            .map(|(_, node)| node.payload().id.clone())
+            .collect::<HashSet<_>>();
+
+        let mut ids = self
+            .evaluate(kasten, &all, &looked_up)
+            .into_iter()
+            .collect::<Vec<_>>();
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Every term inside of this query.
+    fn terms(&self) -> Vec<&Term> {
+        match self {
+            Query::And(queries) | Query::Or(queries) => {
+                queries.iter().flat_map(Query::terms).collect()
+            }
+            Query::Not(query) => query.terms(),
+            Query::Term(term) => vec![term],
+        }
+    }
+
+    fn evaluate(
+        &self,
+        kasten: &Kasten,
+        all: &HashSet<ZettelId>,
+        looked_up: &HashMap<&Term, HashSet<ZettelId>>,
+    ) -> HashSet<ZettelId> {
+        match self {
+            Query::And(queries) => {
+                let mut matching = all.clone();
+                for query in queries {
+                    if matching.is_empty() {
+                        break;
+                    }
+                    let other = query.evaluate(kasten, all, looked_up);
+                    matching.retain(|id| other.contains(id));
+                }
+                matching
+            }
+            Query::Or(queries) => queries
+                .iter()
+                .flat_map(|query| query.evaluate(kasten, all, looked_up))
+                .collect(),
+            Query::Not(query) => {
+                let other = query.evaluate(kasten, all, looked_up);
+                all.difference(&other).cloned().collect()
+            }
+            Query::Term(term) => match looked_up.get(term) {
+                // zettels that aren't part of the graph can't be matched
+                Some(ids) => ids.intersection(all).cloned().collect(),
+                None => term.evaluate(kasten),
+            },
+        }
+    }
+}
+
+impl Term {
+    /// Evaluates a term that only needs the graph.
+    fn evaluate(&self, kasten: &Kasten) -> HashSet<ZettelId> {
+        let zettels = || kasten.graph.nodes_iter().map(|(_, node)| node.payload());
+
+        match self {
+            Term::LinksTo(target) => Self::resolve(kasten, target)
+                .map(|id| {
+                    kasten
+                        .backlinks(&id)
+                        .iter()
+                        .map(|backlink| backlink.source.clone())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            Term::LinkedFrom(source) => Self::resolve(kasten, source)
+                .and_then(|id| kasten.zettel(&id))
+                .map(|zettel| zettel.links.iter().map(|link| link.dest.clone()).collect())
+                .unwrap_or_default(),
+            Term::HasLinks => zettels()
+                .filter(|zettel| !zettel.links.is_empty())
+                .map(|zettel| zettel.id.clone())
+                .collect(),
+            Term::HasBacklinks => zettels()
+                .filter(|zettel| !kasten.backlinks(&zettel.id).is_empty())
+                .map(|zettel| zettel.id.clone())
+                .collect(),
+            Term::Created(comparison, date) => zettels()
+                .filter(|zettel| comparison.holds(zettel.front_matter.created_at.date().cmp(date)))
+                .map(|zettel| zettel.id.clone())
+                .collect(),
+            Term::Title(text) => {
+                let text = text.to_lowercase();
+                zettels()
+                    .filter(|zettel| zettel.front_matter.title.to_lowercase().contains(&text))
+                    .map(|zettel| zettel.id.clone())
+                    .collect()
+            }
+            // these are looked up in the db
+            Term::Tag(_) | Term::Property(_) | Term::Text(_) => HashSet::new(),
+        }
+    }
+
+    /// Finds the zettel `target` refers to, by id or else by title.
+    fn resolve(kasten: &Kasten, target: &str) -> Option<ZettelId> {
+        let id = ZettelId::from(target);
+        if kasten.zettel(&id).is_some() {
+            return Some(id);
+        }
+
+        kasten
+            .graph
+            .nodes_iter()
+            .map(|(_, node)| node.payload())
+            .find(|zettel| zettel.front_matter.title.eq_ignore_ascii_case(target))
+            .map(|zettel| zettel.id.clone())
+    }
+
+    /// Parses a single term, like `tag:rust` or `created>=2025-01-01`. `quoted` terms are always
+    /// full text.
+    fn parse(word: &str, quoted: bool) -> ZkResult<Self> {
+        if quoted {
+            return Ok(Term::Text(word.to_owned()));
+        }
+
+        // `created` on its own or as part of a longer word is just text
+        if let Some(rest) = word
+            .strip_prefix("created")
+            .filter(|rest| rest.starts_with(['<', '>', '=', ':']))
+        {
+            let (comparison, date) = [
+                (">=", Comparison::GreaterOrEqual),
+                ("<=", Comparison::LessOrEqual),
+                (">", Comparison::Greater),
+                ("<", Comparison::Less),
+                ("=", Comparison::Equal),
+                (":", Comparison::Equal),
+            ]
+            .into_iter()
+            .find_map(|(op, comparison)| Some((comparison, rest.strip_prefix(op)?)))
+            .ok_or_else(|| {
+                ZkError::ParseError(format!(
+                    "{word:?} has to compare the date like created>2025-01-01"
+                ))
+            })?;
+
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+                ZkError::ParseError(format!("{date:?} isn't a date like 2025-01-01"))
+            })?;
+            return Ok(Term::Created(comparison, date));
+        }
+
+        let text = || Ok(Term::Text(word.to_owned()));
+        let Some((key, value)) = word.split_once(':') else {
+            return text();
+        };
+        // words like `http://example.com` or `note:` aren't fields
+        if !["tag", "links-to", "linked-from", "has", "title", "prop"].contains(&key) {
+            return text();
+        }
+        if value.is_empty() {
+            return Err(ZkError::ParseError(format!("{word:?} has no value")));
+        }
+
+        match key {
+            "tag" => Ok(Term::Tag(Tag::normalize(value.trim_start_matches('#')))),
+            "links-to" => Ok(Term::LinksTo(value.to_owned())),
+            "linked-from" => Ok(Term::LinkedFrom(value.to_owned())),
+            "has" => match value {
+                "links" => Ok(Term::HasLinks),
+                "backlinks" => Ok(Term::HasBacklinks),
+                _ => Err(ZkError::ParseError(format!(
+                    "expected has:links or has:backlinks, got {word:?}"
+                ))),
+            },
+            "title" => Ok(Term::Title(value.to_owned())),
+            "prop" => Ok(Term::Property(match value.split_once('=') {
+                Some(_) => value.parse()?,
+                None => PropertyFilter::IsSet(value.to_owned()),
+            })),
+            _ => text(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    /// A word, `quoted` if the whole of it was written inside of quotes
+    Word {
+        text: String,
+        quoted: bool,
+    },
+}
+
+fn tokenize(s: &str) -> ZkResult<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            c => {
+                // only a word that starts with a quote is full text, `title:"a b"` isn't
+                let quoted = c == '"';
+                let mut in_quotes = quoted;
+                let mut text = String::new();
+                if !quoted {
+                    text.push(c);
+                }
+
+                while let Some(&c) = chars.peek() {
+                    if !in_quotes && (c.is_whitespace() || c == '(' || c == ')') {
+                        break;
+                    }
+                    chars.next();
+                    match c {
+                        '"' => in_quotes = !in_quotes,
+                        c => text.push(c),
+                    }
+                }
+                if in_quotes {
+                    return Err(ZkError::ParseError(format!("{s:?} has an unclosed quote")));
+                }
+                tokens.push(Token::Word { text, quoted });
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Peekable<IntoIter<Token>>,
+}
+
+impl Parser {
+    fn is_keyword(&mut self, keyword: &str) -> bool {
+        matches!(self.tokens.peek(), Some(Token::Word { text, quoted: false }) if text == keyword)
+    }
+
+    fn or(&mut self) -> ZkResult<Query> {
+        let mut queries = vec![self.and()?];
+        while self.is_keyword("OR") {
+            self.tokens.next();
+            queries.push(self.and()?);
+        }
+        Ok(if queries.len() == 1 {
+            queries.remove(0)
+        } else {
+            Query::Or(queries)
+        })
+    }
+
+    fn and(&mut self) -> ZkResult<Query> {
+        let mut queries = vec![self.unary()?];
+        loop {
+            if self.is_keyword("AND") {
+                self.tokens.next();
+            } else if self.is_keyword("OR")
+                || matches!(self.tokens.peek(), None | Some(Token::Close))
+            {
+                break;
+            }
+            queries.push(self.unary()?);
+        }
+        Ok(if queries.len() == 1 {
+            queries.remove(0)
+        } else {
+            Query::And(queries)
+        })
+    }
+
+    fn unary(&mut self) -> ZkResult<Query> {
+        match self.tokens.next() {
+            Some(Token::Open) => {
+                let query = self.or()?;
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(query),
+                    _ => Err(ZkError::ParseError("missing a closing )".to_owned())),
+                }
+            }
+            Some(Token::Word {
+                text,
+                quoted: false,
+            }) if text == "NOT" || text == "-" => Ok(Query::Not(Box::new(self.unary()?))),
+            Some(Token::Word {
+                text,
+                quoted: false,
+            }) if text.len() > 1 && text.starts_with('-') => Ok(Query::Not(Box::new(Query::Term(
+                Term::parse(&text[1..], false)?,
+            )))),
+            Some(Token::Word { text, quoted }) => Ok(Query::Term(Term::parse(&text, quoted)?)),
+            Some(Token::Close) => Err(ZkError::ParseError("unexpected )".to_owned())),
+            None => Err(ZkError::ParseError("the query ends too early".to_owned())),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = ZkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+        if tokens.is_empty() {
+            return Err(ZkError::ParseError("the query is empty".to_owned()));
+        }
+
+        let mut parser = Parser {
+            tokens: tokens.into_iter().peekable(),
+        };
+        let query = parser.or()?;
+        if parser.tokens.next().is_some() {
+            return Err(ZkError::ParseError("unexpected )".to_owned()));
+        }
+        Ok(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::{Comparison, PropertyFilter, Query, Term};
+
+    fn term(term: Term) -> Query {
+        Query::Term(term)
+    }
+
+    #[test]
+    fn test_parse() {
+        let query: Query =
+            "tag:Rust AND -tag:draft links-to:abc created>2025-01-01 \"borrow checker\""
+                .parse()
+                .expect("valid query");
+        assert_eq!(
+            query,
+            Query::And(vec![
+                term(Term::Tag("rust".to_owned())),
+                Query::Not(Box::new(term(Term::Tag("draft".to_owned())))),
+                term(Term::LinksTo("abc".to_owned())),
+                term(Term::Created(
+                    Comparison::Greater,
+                    NaiveDate::from_ymd_opt(2025, 1, 1).expect("valid date")
+                )),
+                term(Term::Text("borrow checker".to_owned())),
+            ])
+        );
+
+        let query: Query = "title:\"two words\" OR NOT (prop:status=draft has:links)"
+            .parse()
+            .expect("valid query");
+        assert_eq!(
+            query,
+            Query::Or(vec![
+                term(Term::Title("two words".to_owned())),
+                Query::Not(Box::new(Query::And(vec![
+                    term(Term::Property(PropertyFilter::Equals {
+                        key: "status".to_owned(),
+                        value: "draft".to_owned()
+                    })),
+                    term(Term::HasLinks),
+                ]))),
+            ])
+        );
+
+        for invalid in [
+            "",
+            "(tag:a",
+            "tag:a)",
+            "tag:",
+            "created>yesterday",
+            "\"open",
+            "has:everything",
+            "tag:a OR",
+        ] {
+            assert!(invalid.parse::<Query>().is_err(), "{invalid:?}");
+        }
+
+        // words that only look like fields are text
+        for text in [
+            "created",
+            "createdness",
+            "created~2025",
+            "note:",
+            "nope:x",
+            "http://example.com",
+        ] {
+            assert_eq!(
+                text.parse::<Query>().expect("valid query"),
+                term(Term::Text(text.to_owned())),
+                "{text:?}"
+            );
+        }
+    }
+}
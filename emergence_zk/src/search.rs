@@ -0,0 +1,121 @@
+use sea_orm::{ConnectionTrait, DbBackend, FromQueryResult, Statement};
+use serde::{Deserialize, Serialize};
+
+use crate::{Workspace, Zettel, ZettelId, ZkResult};
+
+/// Maximum number of hits returned by a single search
+const SEARCH_LIMIT: u32 = 50;
+
+/// A zettel matching a full-text search.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub id: ZettelId,
+    pub title: String,
+    /// Part of the content around the match, with the matching terms wrapped in `**`
+    pub snippet: String,
+    /// Lower is a better match
+    pub rank: f64,
+}
+
+#[derive(FromQueryResult)]
+struct SearchRow {
+    nanoid: String,
+    title: String,
+    snippet: String,
+    rank: f64,
+}
+
+impl SearchHit {
+    /// Searches the title and content of every indexed zettel for all the words inside `query`,
+    /// returning the best matches first.
+    pub async fn query(query: &str, ws: &Workspace) -> ZkResult<Vec<Self>> {
+        // quote every term so user input can't be interpreted as fts5 query syntax
+        let match_expr = query
+            .split_whitespace()
+            .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        if match_expr.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let stmt = Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            "SELECT nanoid, title, snippet(zettel_fts, 2, '**', '**', '...', 16) AS snippet, \
+             bm25(zettel_fts) AS rank \
+             FROM zettel_fts WHERE zettel_fts MATCH ? ORDER BY rank LIMIT ?",
+            [match_expr.into(), SEARCH_LIMIT.into()],
+        );
+
+        let rows = SearchRow::find_by_statement(stmt)
+            .all(ws.db.as_ref())
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SearchHit {
+                id: row.nanoid.as_str().into(),
+                title: row.title,
+                snippet: row.snippet,
+                rank: row.rank,
+            })
+            .collect())
+    }
+}
+
+/// Returns the ids of every indexed zettel whose title or content contains `phrase`, with its
+/// words right next to each other.
+pub(crate) async fn phrase_matches(phrase: &str, ws: &Workspace) -> ZkResult<Vec<ZettelId>> {
+    if phrase.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    let stmt = Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        "SELECT nanoid FROM zettel_fts WHERE zettel_fts MATCH ?",
+        [format!("\"{}\"", phrase.replace('"', "\"\"")).into()],
+    );
+
+    let rows = ws.db.as_ref().query_all_raw(stmt).await?;
+    rows.iter()
+        .map(|row| {
+            let id: String = row.try_get("", "nanoid")?;
+            Ok(ZettelId::from(id.as_str()))
+        })
+        .collect()
+}
+
+/// Replaces whatever the search index has for `zettel` with its current title and content.
+pub(crate) async fn index_zettel(zettel: &Zettel, ws: &Workspace) -> ZkResult<()> {
+    remove_zettel(&zettel.id, ws).await?;
+
+    ws.db
+        .as_ref()
+        .execute_raw(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            "INSERT INTO zettel_fts (nanoid, title, content) VALUES (?, ?, ?)",
+            [
+                zettel.id.to_string().into(),
+                zettel.front_matter.title.clone().into(),
+                zettel.content.clone().into(),
+            ],
+        ))
+        .await?;
+
+    Ok(())
+}
+
+/// Removes the zettel with the given `id` from the search index.
+pub(crate) async fn remove_zettel(id: &ZettelId, ws: &Workspace) -> ZkResult<()> {
+    ws.db
+        .as_ref()
+        .execute_raw(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            "DELETE FROM zettel_fts WHERE nanoid = ?",
+            [id.to_string().into()],
+        ))
+        .await?;
+
+    Ok(())
+}
@@ -1,76 +1,505 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+    sync::Arc,
+};
 
-use sea_orm::{ActiveModelTrait, ActiveValue::Set};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, Condition, ConnectionTrait, DbBackend,
+    EntityTrait, QueryFilter, QueryOrder, QuerySelect, Statement, sea_query::Expr,
+};
 use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
 
-use crate::{Workspace, ZettelId, ZkError, ZkResult, entities::prelude::*, entities::tag};
+use crate::{
+    Color, Workspace, ZettelId, ZkError, ZkResult, entities::prelude::*, entities::tag,
+    entities::zettel_tag,
+};
 
-//TODO: think about how we want to deal with tags
+/// Separates the levels of a hierarchical tag, as in `project/emergence/lsp`.
+pub const TAG_SEPARATOR: char = '/';
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Tag {
+    /// The full name of the tag, including all of its ancestors
     pub name: String,
-    //TODO: make this actually something
-    pub color: String,
+    pub color: Color,
 }
 
 pub type TagMap = HashMap<String, Tag>;
 
 impl Tag {
-    pub async fn new(
-        name: impl Into<String>,
-        color: impl Into<String>,
-        ws: &Workspace,
-    ) -> ZkResult<Self> {
-        let name = name.into();
-        let color = color.into();
-        let name = name.to_lowercase();
-
-        if !name.is_ascii() {
-            return Err(ZkError::ParseError("Name isn't valid ascii!".to_owned()));
+    /// Returns the tag called `name` with the given `color`, creating it and any of its missing
+    /// ancestors if needed. A tag that exists already is given `color`.
+    pub async fn new(name: impl Into<String>, color: Color, ws: &Workspace) -> ZkResult<Self> {
+        let name = Self::parse_name(&name.into())?;
+        let registry = TagRegistry::new(ws);
+
+        registry.resolve_row(&name).await?;
+        registry.set_color(&name, color).await
+    }
+
+    /// Returns the tag called `name`, creating it and any of its missing ancestors if needed.
+    pub async fn get_or_new(name: impl Into<String>, ws: &Workspace) -> ZkResult<Self> {
+        TagRegistry::new(ws).resolve(&name.into()).await
+    }
+
+    /// Normalises `name` the way every tag name is stored and compared: it is case folded and
+    /// put into Unicode NFC, and empty levels are dropped, so `Ärger//Ideen/` becomes
+    /// `ärger/ideen`.
+    pub fn normalize(name: &str) -> String {
+        let folded = caseless::default_case_fold_str(&name.trim().nfd().collect::<String>());
+
+        folded
+            .nfc()
+            .collect::<String>()
+            .split(TAG_SEPARATOR)
+            .filter(|level| !level.is_empty())
+            .collect::<Vec<_>>()
+            .join(&TAG_SEPARATOR.to_string())
+    }
+
+    /// Normalises `name` and checks it against the rules for tag names: every level may only
+    /// consist of letters and digits of any script, combining marks, `_` and `-`.
+    ///
+    /// # Errors
+    /// Errors if the normalised name is empty or contains any other character.
+    pub fn parse_name(name: &str) -> ZkResult<String> {
+        let name = Self::normalize(name);
+        if name.is_empty() {
+            return Err(ZkError::ParseError("tag names can't be empty".to_owned()));
+        }
+        if let Some(c) = name
+            .chars()
+            .find(|c| !(is_name_char(*c) || *c == TAG_SEPARATOR))
+        {
+            return Err(ZkError::ParseError(format!(
+                "tag {name:?} can't contain {c:?}"
+            )));
         }
+        Ok(name)
+    }
+
+    /// The name of the tag directly above this one, if any.
+    pub fn parent(&self) -> Option<&str> {
+        parent_name(&self.name)
+    }
 
-        let _ = tag::ActiveModel {
-            nanoid: sea_orm::ActiveValue::Set(ZettelId::default().to_string()),
-            name: Set(name.to_owned()),
-            color: Set(color.to_owned()),
-            ..Default::default()
+    /// The last level of the name, e.g. `lsp` for `project/emergence/lsp`.
+    pub fn leaf(&self) -> &str {
+        self.name
+            .rsplit_once(TAG_SEPARATOR)
+            .map_or(self.name.as_str(), |(_, leaf)| leaf)
+    }
+
+    /// Whether this tag is `ancestor` itself or lies somewhere below it.
+    pub fn is_within(&self, ancestor: &str) -> bool {
+        is_within(&self.name, ancestor)
+    }
+
+    /// Returns the ids of every zettel tagged with `name` or any tag below it.
+    pub async fn zettels(name: &str, ws: &Workspace) -> ZkResult<Vec<ZettelId>> {
+        let name = Self::normalize(name);
+
+        // `LIKE` would treat the `_` tag names may contain as a wildcard
+        let below = format!("{name}{TAG_SEPARATOR}");
+        let is_below = Expr::cust_with_values(
+            "substr(tag.name, 1, ?) = ?",
+            [
+                sea_orm::Value::from(below.chars().count() as i64),
+                below.into(),
+            ],
+        );
+
+        let ids = ZettelTag::find()
+            .inner_join(TagEntity)
+            .select_only()
+            .column(zettel_tag::Column::ZettelNanoId)
+            .distinct()
+            .filter(
+                Condition::any()
+                    .add(tag::Column::Name.eq(&name))
+                    .add(is_below),
+            )
+            .order_by_asc(zettel_tag::Column::ZettelNanoId)
+            .into_tuple::<String>()
+            .all(ws.db.as_ref())
+            .await?;
+
+        Ok(ids.iter().map(|id| ZettelId::from(id.as_str())).collect())
+    }
+
+    /// Returns every tag in the kasten arranged by hierarchy, along with how many zettels use
+    /// each level.
+    pub async fn tree(ws: &Workspace) -> ZkResult<Vec<TagNode>> {
+        let tags = TagEntity::find().all(ws.db.as_ref()).await?;
+
+        let mut levels: BTreeMap<String, Option<Color>> = BTreeMap::new();
+        for tag in tags {
+            // tags that predate hierarchies may be missing their ancestors
+            let mut ancestor = parent_name(&tag.name);
+            while let Some(name) = ancestor {
+                levels.entry(name.to_owned()).or_default();
+                ancestor = parent_name(name);
+            }
+            let tag = Tag::from(tag);
+            levels.insert(tag.name, Some(tag.color));
         }
-        .save(ws.db.as_ref())
-        .await?;
 
-        //TODO: color validation or something
+        let counts = Self::counts(ws).await?;
 
-        // we can do some parse validation here
-        Ok(Self {
-            name: name.to_owned(),
-            color: color.to_owned(),
-        })
+        let mut children: HashMap<Option<&str>, Vec<&str>> = HashMap::new();
+        for name in levels.keys() {
+            children
+                .entry(parent_name(name))
+                .or_default()
+                .push(name.as_str());
+        }
+
+        Ok(children
+            .get(&None)
+            .into_iter()
+            .flatten()
+            .map(|root| TagNode::build(root, &levels, &counts, &children))
+            .collect())
     }
 
-    pub async fn get_or_new(name: impl Into<String>, ws: &Workspace) -> ZkResult<Self> {
-        let name = name.into();
-        if let Some(existing) = TagEntity::find_by_name(&name).one(ws.db.as_ref()).await? {
-            Ok(existing.into())
-        } else {
-            Self::new(name, "random!", ws).await
+    /// Returns how many zettels have exactly each level of the tag hierarchy and how many have it
+    /// or any tag below it, by name. Levels no zettel uses are left out.
+    async fn counts(ws: &Workspace) -> ZkResult<HashMap<String, (usize, usize)>> {
+        // every tag along with each of its ancestors, `own` only for the tag itself. The parent
+        // of `a/bc` is found by trimming every character but `/` off its end, then the `/`.
+        let stmt = Statement::from_string(
+            DbBackend::Sqlite,
+            "WITH RECURSIVE level(name, tag_nano_id, own) AS ( \
+                 SELECT name, nanoid, 1 FROM tag \
+                 UNION \
+                 SELECT rtrim(rtrim(name, replace(name, '/', '')), '/'), tag_nano_id, 0 \
+                 FROM level WHERE instr(name, '/') > 0 \
+             ) \
+             SELECT level.name AS name, \
+             COUNT(DISTINCT CASE WHEN level.own THEN zettel_tag.zettel_nano_id END) AS count, \
+             COUNT(DISTINCT zettel_tag.zettel_nano_id) AS total \
+             FROM level JOIN zettel_tag ON zettel_tag.tag_nano_id = level.tag_nano_id \
+             GROUP BY level.name",
+        );
+
+        let rows = ws.db.as_ref().query_all_raw(stmt).await?;
+        rows.iter()
+            .map(|row| {
+                let name: String = row.try_get("", "name")?;
+                let count: i64 = row.try_get("", "count")?;
+                let total: i64 = row.try_get("", "total")?;
+                Ok((name, (count as usize, total as usize)))
+            })
+            .collect()
+    }
+}
+
+/// Resolves tag names to the rows of the `tag` table, so every name has exactly one row, and
+/// keeps that table in line with the tags zettels actually use.
+#[derive(Clone, Debug)]
+pub struct TagRegistry<'a> {
+    ws: &'a Workspace,
+    used_colors: UsedColors,
+}
+
+/// The colors tags have already, read from the db the first time a new tag needs one. Sharing
+/// it between registries, like every one of a single parse does, means they are only read once.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct UsedColors(Arc<Mutex<Option<HashSet<Color>>>>);
+
+impl<'a> TagRegistry<'a> {
+    pub fn new(ws: &'a Workspace) -> Self {
+        Self {
+            ws,
+            used_colors: UsedColors::default(),
         }
     }
+
+    /// Uses `used_colors` to pick the colors of new tags, sharing them with every other registry
+    /// using it.
+    pub(crate) fn with_used_colors(mut self, used_colors: &UsedColors) -> Self {
+        self.used_colors = used_colors.clone();
+        self
+    }
+
+    /// Returns the tag called `name`, if it exists.
+    pub async fn get(&self, name: &str) -> ZkResult<Option<Tag>> {
+        Ok(TagEntity::find_by_name(Tag::normalize(name))
+            .one(self.ws.db.as_ref())
+            .await?
+            .map(Tag::from))
+    }
+
+    /// Returns the tag called `name`, creating it and any of its missing ancestors if needed.
+    pub async fn resolve(&self, name: &str) -> ZkResult<Tag> {
+        let name = Tag::parse_name(name)?;
+        Ok(self.resolve_row(&name).await?.into())
+    }
+
+    /// Returns every tag, sorted by name.
+    pub async fn all(&self) -> ZkResult<Vec<Tag>> {
+        let mut tags = TagEntity::find()
+            .all(self.ws.db.as_ref())
+            .await?
+            .into_iter()
+            .map(Tag::from)
+            .collect::<Vec<_>>();
+        tags.sort();
+        Ok(tags)
+    }
+
+    /// Finds or inserts the tag row for the already normalised `name`, along with every ancestor
+    /// on the way to it, fixing up parents that are missing from older rows.
+    pub(crate) async fn resolve_row(&self, name: &str) -> ZkResult<tag::Model> {
+        let mut parent: Option<tag::Model> = None;
+
+        for (end, _) in name
+            .match_indices(TAG_SEPARATOR)
+            .chain(std::iter::once((name.len(), "")))
+        {
+            let level = &name[..end];
+            let parent_nano_id = parent.as_ref().map(|parent| parent.nanoid.clone());
+
+            let model = match TagEntity::find_by_name(level)
+                .one(self.ws.db.as_ref())
+                .await?
+            {
+                Some(model) if model.parent_nano_id != parent_nano_id => {
+                    let mut am: tag::ActiveModel = model.into();
+                    am.parent_nano_id = Set(parent_nano_id);
+                    am.update(self.ws.db.as_ref()).await?
+                }
+                Some(model) => model,
+                None => {
+                    let inserted = tag::ActiveModel {
+                        nanoid: Set(ZettelId::generate(&self.ws.config).to_string()),
+                        name: Set(level.to_owned()),
+                        color: Set(self.pick_color(level).await?.to_string()),
+                        parent_nano_id: Set(parent_nano_id),
+                        ..Default::default()
+                    }
+                    .insert(self.ws.db.as_ref())
+                    .await;
+
+                    match inserted {
+                        Ok(model) => model,
+                        // another zettel may have added the same tag in the meantime
+                        Err(e) => TagEntity::find_by_name(level)
+                            .one(self.ws.db.as_ref())
+                            .await?
+                            .ok_or(e)?,
+                    }
+                }
+            };
+
+            parent = Some(model);
+        }
+
+        Ok(parent.expect("a tag name has at least one level"))
+    }
+
+    /// Returns the color a new tag called `name` gets: the configured `default_tag_color`, or
+    /// else the palette color belonging to its name, skipping ahead to one no other tag has yet.
+    pub(crate) async fn pick_color(&self, name: &str) -> ZkResult<Color> {
+        if let Some(color) = self.ws.config.fixed_tag_color()? {
+            return Ok(color);
+        }
+
+        let mut used = self.used_colors.0.lock().await;
+        if used.is_none() {
+            let colors = TagEntity::find()
+                .all(self.ws.db.as_ref())
+                .await?
+                .into_iter()
+                .map(|tag| Tag::from(tag).color)
+                .collect();
+            *used = Some(colors);
+        }
+        let used = used.as_mut().expect("loaded above");
+
+        let color = Color::from_palette_avoiding(name, used);
+        used.insert(color);
+        Ok(color)
+    }
+
+    /// Changes the color of the tag `name`.
+    ///
+    /// # Errors
+    /// Errors if there is no such tag.
+    pub async fn set_color(&self, name: &str, color: Color) -> ZkResult<Tag> {
+        let name = Tag::normalize(name);
+        let row = TagEntity::find_by_name(&name)
+            .one(self.ws.db.as_ref())
+            .await?
+            .ok_or_else(|| ZkError::InvalidOperation(format!("there is no tag {name:?}")))?;
+
+        let mut am: tag::ActiveModel = row.into();
+        am.color = Set(color.to_string());
+        Ok(am.update(self.ws.db.as_ref()).await?.into())
+    }
+
+    /// Deletes every tag that no zettel uses, neither directly nor through a tag below it.
+    ///
+    /// Returns the names of the deleted tags, sorted.
+    pub async fn remove_orphans(&self) -> ZkResult<Vec<String>> {
+        let tags = TagEntity::find().all(self.ws.db.as_ref()).await?;
+        let used = ZettelTag::find()
+            .select_only()
+            .column(zettel_tag::Column::TagNanoId)
+            .distinct()
+            .into_tuple::<String>()
+            .all(self.ws.db.as_ref())
+            .await?
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        let mut keep = HashSet::new();
+        for tag in tags.iter().filter(|tag| used.contains(&tag.nanoid)) {
+            let mut name = Some(tag.name.as_str());
+            while let Some(level) = name {
+                keep.insert(level);
+                name = parent_name(level);
+            }
+        }
+
+        let (mut orphans, nano_ids): (Vec<_>, Vec<_>) = tags
+            .iter()
+            .filter(|tag| !keep.contains(tag.name.as_str()))
+            .map(|tag| (tag.name.clone(), tag.nanoid.clone()))
+            .unzip();
+
+        if !nano_ids.is_empty() {
+            TagEntity::delete_many()
+                .filter(tag::Column::Nanoid.is_in(nano_ids))
+                .exec(self.ws.db.as_ref())
+                .await?;
+        }
+
+        orphans.sort();
+        Ok(orphans)
+    }
+
+    /// Renames the row of the already normalised `old` and of every tag below it, so they keep
+    /// their color. Rows whose new name is taken already are left alone, as the zettels using
+    /// them get linked to the existing tags once their front matter is synced.
+    pub(crate) async fn rename_rows(&self, old: &str, new: &str) -> ZkResult<()> {
+        let mut rows = TagEntity::find()
+            .all(self.ws.db.as_ref())
+            .await?
+            .into_iter()
+            .filter(|row| is_within(&row.name, old))
+            .collect::<Vec<_>>();
+        // parents have to be renamed before their children
+        rows.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for row in rows {
+            let name = format!("{new}{}", &row.name[old.len()..]);
+            if TagEntity::find_by_name(&name)
+                .one(self.ws.db.as_ref())
+                .await?
+                .is_some()
+            {
+                continue;
+            }
+
+            let parent_nano_id = match parent_name(&name) {
+                Some(parent) => Some(self.resolve_row(parent).await?.nanoid),
+                None => None,
+            };
+
+            let mut am: tag::ActiveModel = row.into();
+            am.name = Set(name);
+            am.parent_nano_id = Set(parent_nano_id);
+            am.update(self.ws.db.as_ref()).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// One level of the tag hierarchy.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagNode {
+    /// The full name of the tag
+    pub name: String,
+    /// The color of the tag, `None` if only its descendants exist in the db
+    pub color: Option<Color>,
+    /// How many zettels have exactly this tag
+    pub count: usize,
+    /// How many zettels have this tag or any tag below it
+    pub total: usize,
+    pub children: Vec<TagNode>,
+}
+
+impl TagNode {
+    /// The last level of the name, e.g. `lsp` for `project/emergence/lsp`.
+    pub fn leaf(&self) -> &str {
+        self.name
+            .rsplit_once(TAG_SEPARATOR)
+            .map_or(self.name.as_str(), |(_, leaf)| leaf)
+    }
+
+    /// Builds the node for `name` and everything below it.
+    fn build(
+        name: &str,
+        levels: &BTreeMap<String, Option<Color>>,
+        counts: &HashMap<String, (usize, usize)>,
+        children: &HashMap<Option<&str>, Vec<&str>>,
+    ) -> Self {
+        let (count, total) = counts.get(name).copied().unwrap_or_default();
+
+        Self {
+            name: name.to_owned(),
+            color: levels[name],
+            count,
+            total,
+            children: children
+                .get(&Some(name))
+                .into_iter()
+                .flatten()
+                .map(|child| Self::build(child, levels, counts, children))
+                .collect(),
+        }
+    }
+}
+
+/// Whether `c` may be part of a level of a tag name.
+pub(crate) fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || is_combining_mark(c) || matches!(c, '_' | '-')
+}
+
+fn parent_name(name: &str) -> Option<&str> {
+    name.rsplit_once(TAG_SEPARATOR).map(|(parent, _)| parent)
+}
+
+/// Whether the tag `name` is `ancestor` itself or lies somewhere below it.
+pub(crate) fn is_within(name: &str, ancestor: &str) -> bool {
+    name.strip_prefix(ancestor)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(TAG_SEPARATOR))
+}
+
+/// Parses the color stored for the tag `name`, older kastens stored placeholders like `random`
+/// in place of colors.
+fn stored_color(name: &str, color: &str) -> Color {
+    color.parse().unwrap_or_else(|_| Color::from_palette(name))
 }
 
 impl From<tag::ModelEx> for Tag {
     fn from(value: tag::ModelEx) -> Self {
         Tag {
+            color: stored_color(&value.name, &value.color),
             name: value.name,
-            color: value.color,
         }
     }
 }
 impl From<tag::Model> for Tag {
     fn from(value: tag::Model) -> Self {
         Tag {
+            color: stored_color(&value.name, &value.color),
             name: value.name,
-            color: value.color,
         }
     }
 }
@@ -80,3 +509,25 @@ impl Display for Tag {
         write!(f, "{}", self.name)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_normalized() {
+        assert_eq!(
+            Tag::normalize(" Project//Emergence/LSP/ "),
+            "project/emergence/lsp"
+        );
+        assert_eq!(Tag::normalize("/"), "");
+    }
+
+    #[test]
+    fn descendants_are_within_their_ancestors() {
+        assert!(is_within("project/emergence", "project"));
+        assert!(is_within("project", "project"));
+        assert!(!is_within("projects", "project"));
+        assert!(!is_within("project", "project/emergence"));
+    }
+}
@@ -1,16 +1,35 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::{EmergenceDb, ZkResult};
+use crate::{EmergenceDb, KastenConfig, ZkResult};
 #[derive(Clone, Debug)]
 pub struct Workspace {
     pub root: PathBuf,
     pub db: EmergenceDb,
+    /// Settings of the kasten, read from `.emergence/config.toml`
+    pub config: KastenConfig,
 }
 
 impl Workspace {
     pub async fn new(root: impl Into<PathBuf>) -> ZkResult<Self> {
         let root = root.into();
+        // an invalid config should fail before we touch the db
+        let config = KastenConfig::load(&root)?;
         let db = EmergenceDb::connect(&root).await?;
-        Ok(Self { root, db })
+        Ok(Self { root, db, config })
+    }
+
+    /// Returns `path` relative to the root of this workspace, which is how paths are stored in
+    /// the db.
+    pub fn relative_path(&self, path: &Path) -> PathBuf {
+        if let Ok(relative) = path.strip_prefix(&self.root) {
+            return relative.to_path_buf();
+        }
+
+        // paths coming from the file watcher are canonical, the root might not be
+        self.root
+            .canonicalize()
+            .ok()
+            .and_then(|root| path.strip_prefix(root).ok().map(Path::to_path_buf))
+            .unwrap_or_else(|| path.to_path_buf())
     }
 }
@@ -1,5 +1,6 @@
-use std::fs::OpenOptions;
+use std::fs::{self, OpenOptions};
 use std::io::Write;
+use std::path::Path;
 
 use chrono::Local;
 use sea_orm::ActiveModelTrait as _;
@@ -13,7 +14,7 @@ pub struct ZettelBuilder<'a> {
 
 impl<'a> ZettelBuilder<'a> {
     pub fn new(ws: &'a Workspace) -> Self {
-        let id = ZettelId::default();
+        let id = ZettelId::generate(&ws.config);
 
         let zettel_path = {
             let mut project_root = ws.root.clone();
@@ -21,7 +22,8 @@ impl<'a> ZettelBuilder<'a> {
             project_root
         };
 
-        let front_matter = FrontMatter::new("", Local::now().naive_local(), Vec::<String>::new());
+        let front_matter = FrontMatter::new("", Local::now().naive_local(), Vec::<String>::new())
+            .with_date_format(&ws.config.date_format);
 
         ZettelBuilder {
             ws,
@@ -32,6 +34,9 @@ impl<'a> ZettelBuilder<'a> {
                 content: "".to_owned(),
                 tags: Vec::new(),
                 links: vec![],
+                dangling_links: vec![],
+                external_links: vec![],
+                content_line_offset: 0,
             },
         }
     }
@@ -43,6 +48,9 @@ impl<'a> ZettelBuilder<'a> {
     }
 
     pub fn add_tag(&mut self, tag: Tag) {
+        if !self.inner.front_matter.tag_strings.contains(&tag.name) {
+            self.inner.front_matter.tag_strings.push(tag.name.clone());
+        }
         self.inner.tags.push(tag);
     }
 
@@ -58,7 +66,7 @@ impl<'a> ZettelBuilder<'a> {
     }
 
     pub fn with_additional_tag(mut self, tag: Tag) -> Self {
-        self.inner.tags.push(tag);
+        self.add_tag(tag);
         self
     }
 
@@ -68,12 +76,23 @@ impl<'a> ZettelBuilder<'a> {
         self
     }
 
+    /// Puts the zettel inside of `dir`, relative to the root of the kasten.
+    pub fn with_directory(mut self, dir: impl AsRef<Path>) -> Self {
+        let file_name = [self.inner.id.as_str(), ".md"].join("");
+        self.inner.path = self.ws.root.join(dir).join(file_name);
+        self
+    }
+
     pub async fn build(mut self) -> ZkResult<Zettel> {
         let now = Local::now().naive_local();
 
         // set created_at to build time
         self.inner.front_matter.created_at = now;
 
+        if let Some(dir) = self.inner.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
         let mut f = OpenOptions::new()
             .create_new(true)
             .read(true)
@@ -82,10 +101,18 @@ impl<'a> ZettelBuilder<'a> {
 
         writeln!(f, "{}", self.inner.front_matter)?;
         writeln!(f, "{}", self.inner.content)?;
+        // the front matter is followed by a blank line
+        self.inner.content_line_offset = self.inner.front_matter.to_string().lines().count() + 1;
 
         let am = entities::zettel::ActiveModel {
             nanoid: sea_orm::ActiveValue::Set(self.inner.id.to_string()),
             title: sea_orm::ActiveValue::Set(self.inner.front_matter.title.clone()),
+            path: sea_orm::ActiveValue::Set(Some(
+                self.ws
+                    .relative_path(&self.inner.path)
+                    .to_string_lossy()
+                    .into_owned(),
+            )),
             ..Default::default()
         };
 
@@ -1,17 +1,64 @@
 use std::{fmt::Display, fs, path::Path};
 
-use chrono::{NaiveDateTime, format::StrftimeItems};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, format::StrftimeItems};
 use serde::{Deserialize, Serialize};
+use serde_yaml_ng::{Mapping, Value};
 
-use crate::{ZkError, ZkResult};
+use crate::{KastenConfig, Properties, PropertyValue, Tag, ZkError, ZkResult, yaml_key};
 
-const DATE_FMT_STR: &str = "%Y-%m-%d %I:%M:%S %p";
+/// Format dates are written in inside of legacy front matter, unless the kasten is configured
+/// otherwise
+pub(crate) const DATE_FMT_STR: &str = "%Y-%m-%d %I:%M:%S %p";
+/// Format dates are written in inside of yaml front matter
+const YAML_DATE_FMT_STR: &str = "%Y-%m-%dT%H:%M:%S";
+/// Formats, besides rfc 3339, that dates inside of yaml front matter are read in
+const YAML_DATE_FMT_STRS: [&str; 5] = [
+    YAML_DATE_FMT_STR,
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+    DATE_FMT_STR,
+];
+
+fn parse_error_at(line: usize, column: usize, message: impl Into<String>) -> ZkError {
+    ZkError::ParseErrorAt {
+        line,
+        column,
+        message: message.into(),
+    }
+}
+
+/// Keys of yaml front matter that aren't custom properties
+const YAML_KEYS: [&str; 4] = ["title", "date", "tags", "aliases"];
+
+/// The way the front matter of a zettel is written, so it can be written back the same way.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub enum FrontMatterFormat {
+    /// Exactly a `Title: `, `Date: ` and `Tags: ` line, in that order
+    #[default]
+    Legacy,
+    /// Standard yaml, as written by most other markdown tools
+    Yaml,
+}
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
 pub struct FrontMatter {
     pub title: String,
     pub created_at: NaiveDateTime,
     pub tag_strings: Vec<String>,
+    /// Other names the zettel goes by
+    pub aliases: Vec<String>,
+    /// Every other key inside the front matter
+    pub properties: Properties,
+    /// Legacy front matter can't hold aliases or properties, if there are any it is written as
+    /// yaml instead
+    pub format: FrontMatterFormat,
+    /// strftime format the date is written in when the format is legacy
+    pub date_format: String,
+    /// The yaml this front matter was read from, keys that still say the same are written back
+    /// exactly like this, in the same order and with their comments
+    #[serde(skip)]
+    pub yaml_source: Option<String>,
 }
 
 impl FrontMatter {
@@ -20,36 +67,85 @@ impl FrontMatter {
         created_at: NaiveDateTime,
         tag_strings: Vec<impl Into<String>>,
     ) -> Self {
-        let tag_strings = tag_strings.into_iter().map(|e| e.into()).collect();
-
         FrontMatter {
             title: title.into(),
             created_at,
-            tag_strings,
+            tag_strings: Self::normalize_tags(tag_strings),
+            aliases: vec![],
+            properties: Properties::default(),
+            format: FrontMatterFormat::default(),
+            date_format: DATE_FMT_STR.to_owned(),
+            yaml_source: None,
+        }
+    }
+
+    /// Tags are compared by their normalised names, so that is how they are written back too.
+    fn normalize_tags(tag_strings: Vec<impl Into<String>>) -> Vec<String> {
+        let mut tags: Vec<String> = vec![];
+        for tag in tag_strings {
+            let tag = Tag::normalize(&tag.into());
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
         }
+        tags
+    }
+
+    pub fn with_format(mut self, format: FrontMatterFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_date_format(mut self, date_format: impl Into<String>) -> Self {
+        self.date_format = date_format.into();
+        self
+    }
+
+    pub fn with_aliases(mut self, aliases: Vec<impl Into<String>>) -> Self {
+        self.aliases = aliases.into_iter().map(Into::into).collect();
+        self
     }
 
     /// Reads in file and returns the front matter as well as the content after it.
-    /// expected format for front matter as follows
-    ///```md
-    /// ---
-    /// Title: LOL
-    /// Date: 2025-01-01 12:50:19 AM
-    /// Tags: Daily barber
-    /// ---
-    /// ```
+    /// See [`FrontMatter::extract_from_str`] for the accepted formats.
+    ///
+    /// Yaml front matter without a `date` gets the modification time of the file.
     pub fn extract_from_file(path: impl AsRef<Path>) -> ZkResult<(Self, String)> {
+        Self::extract_from_file_with(path, &KastenConfig::default())
+    }
+
+    /// Same as [`FrontMatter::extract_from_file`], but legacy dates are read in the date format
+    /// of `config` before falling back to the default one.
+    pub fn extract_from_file_with(
+        path: impl AsRef<Path>,
+        config: &KastenConfig,
+    ) -> ZkResult<(Self, String)> {
+        Self::read_file(path, config).map(|(front_matter, content, _)| (front_matter, content))
+    }
+
+    /// Same as [`FrontMatter::extract_from_file_with`], but also returns how many lines of the
+    /// file come before the content.
+    pub(crate) fn read_file(
+        path: impl AsRef<Path>,
+        config: &KastenConfig,
+    ) -> ZkResult<(Self, String, usize)> {
         let string = fs::read_to_string(&path)?;
-        Self::extract_from_str(&string).map_err(|e| {
-            ZkError::ParseError(format!(
-                "Unable to parse frontmatter from file {:#?}, reason: {e}",
-                path.as_ref()
-            ))
-        })
+        let modified_at = || {
+            fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .map(|modified| DateTime::<Local>::from(modified).naive_local())
+                .unwrap_or_else(|_| Local::now().naive_local())
+        };
+
+        let (front_matter, content) = Self::extract(&string, &config.date_format, modified_at)?;
+        // parsing succeeded, so the front matter is closed
+        let offset = Self::content_line_offset(&string).unwrap_or_default();
+
+        Ok((front_matter, content, offset))
     }
 
     /// Returns the front matter as well as the content after it.
-    /// expected format for front matter as follows
+    /// expected format for front matter is either the legacy one
     ///```md
     /// ---
     /// Title: LOL
@@ -57,71 +153,367 @@ impl FrontMatter {
     /// Tags: Daily barber
     /// ---
     /// ```
+    /// or yaml, where `tags` and `aliases` can be a list or a single string
+    ///```md
+    /// ---
+    /// title: LOL
+    /// date: 2025-01-01T00:50:19
+    /// tags: [daily, barber]
+    /// aliases: lmao
+    /// ---
+    /// ```
+    ///
+    /// Yaml front matter without a `date` gets the current time.
     pub fn extract_from_str(string: impl Into<String>) -> ZkResult<(Self, String)> {
         let string: String = string.into();
-        // we just want to strictly match this, else we error
+        Self::extract(&string, DATE_FMT_STR, || Local::now().naive_local())
+    }
 
+    /// Returns how many lines of `string` come before the content, meaning the front matter and
+    /// its deliminators.
+    fn content_line_offset(string: &str) -> Option<usize> {
         let lines: Vec<_> = string.lines().collect();
+        Self::closing_delim(&lines).map(|end| end + 1)
+    }
 
-        let delim_check = |line_number: usize| -> ZkResult<()> {
-            let delim = lines
-                .get(line_number)
-                .ok_or(ZkError::ParseError(format!(
-                    "Line Number {line_number} doesnt exist!"
-                )))?
-                .trim();
-            if delim != "---" {
-                return Err(ZkError::ParseError(
-                    "FrontMatter Deliminator Corrupted!".to_owned(),
-                ));
-            }
-            Ok(())
-        };
+    /// Returns the index of the line closing the front matter at the start of `lines`.
+    fn closing_delim(lines: &[&str]) -> Option<usize> {
+        let is_delim = |line: &str| line.trim() == "---";
+
+        if !lines.first().is_some_and(|line| is_delim(line)) {
+            return None;
+        }
+
+        lines
+            .iter()
+            .skip(1)
+            .position(|line| is_delim(line))
+            .map(|idx| idx + 1)
+    }
+
+    fn extract(
+        string: &str,
+        date_format: &str,
+        fallback_date: impl FnOnce() -> NaiveDateTime,
+    ) -> ZkResult<(Self, String)> {
+        let lines: Vec<_> = string.lines().collect();
 
         // check first line
-        delim_check(0)?;
+        if !lines.first().is_some_and(|line| line.trim() == "---") {
+            return Err(parse_error_at(1, 1, "FrontMatter Deliminator Corrupted!"));
+        }
+
+        let end = Self::closing_delim(&lines)
+            .ok_or_else(|| parse_error_at(1, 1, "FrontMatter is never closed!"))?;
+
+        let block = &lines[1..end];
+
+        let front_matter = if block.first().is_some_and(|line| line.starts_with("Title:")) {
+            Self::parse_legacy(block, date_format)?
+        } else {
+            Self::parse_yaml(block, fallback_date)?
+        };
+
+        let remaining = lines[end + 1..].join("\n");
+
+        Ok((front_matter, remaining))
+    }
+
+    /// Parses the lines between the deliminators of legacy front matter.
+    fn parse_legacy(block: &[&str], date_format: &str) -> ZkResult<Self> {
+        // we just want to strictly match this, else we error
+        if block.len() != 3 {
+            return Err(parse_error_at(
+                block.len().min(3) + 2,
+                1,
+                format!("expected 3 front matter lines, found {}", block.len()),
+            ));
+        }
 
         //extract name
-        let title = lines
-            .get(1)
-            .ok_or(ZkError::ParseError("Title line doesn't exist!".to_owned()))?
+        let title = block[0]
             .strip_prefix("Title: ")
-            .ok_or(ZkError::ParseError(
-                "Title line doesn't start with \"Title: \" ".to_owned(),
-            ))?;
+            .ok_or_else(|| parse_error_at(2, 1, "Title line doesn't start with \"Title: \" "))?;
 
-        let created_at = lines
-            .get(2)
-            .ok_or(ZkError::ParseError("Date line doesn't exist!".to_owned()))?
+        let date_str = block[1]
             .strip_prefix("Date: ")
-            .ok_or(ZkError::ParseError(
-                "Date line doesn't start with \"Date: \" ".to_owned(),
-            ))
-            .map(|date_str| NaiveDateTime::parse_from_str(date_str, DATE_FMT_STR))?
-            .map_err(|err| ZkError::ParseError(err.to_string()))?;
-
-        let tag_strings: Vec<String> = lines
-            .get(3)
-            .ok_or_else(|| ZkError::ParseError("Tag line doesn't exist!".to_owned()))?
-            .strip_prefix("Tags: ")
-            .ok_or(ZkError::ParseError(
-                "Tag line doesn't start with \"Tags: \" ".to_owned(),
-            ))?
+            .ok_or_else(|| parse_error_at(3, 1, "Date line doesn't start with \"Date: \" "))?;
+
+        // zettels written before the date format was configured still use the default one
+        let (created_at, date_format) = NaiveDateTime::parse_from_str(date_str, date_format)
+            .map(|date| (date, date_format))
+            .or_else(|err| {
+                NaiveDateTime::parse_from_str(date_str, DATE_FMT_STR)
+                    .map(|date| (date, DATE_FMT_STR))
+                    .map_err(|_| err)
+            })
+            .map_err(|err| parse_error_at(3, "Date: ".len() + 1, err.to_string()))?;
+
+        // editors like to strip the trailing space of an empty tag line
+        let tag_strings: Vec<String> = block[2]
+            .strip_prefix("Tags:")
+            .ok_or_else(|| parse_error_at(4, 1, "Tag line doesn't start with \"Tags: \" "))?
             .split_whitespace()
             .map(|e| e.to_owned())
             .collect::<Vec<_>>();
 
-        delim_check(4)?;
+        Ok(FrontMatter::new(title, created_at, tag_strings).with_date_format(date_format))
+    }
+
+    /// Parses the lines between the deliminators of yaml front matter.
+    fn parse_yaml(block: &[&str], fallback_date: impl FnOnce() -> NaiveDateTime) -> ZkResult<Self> {
+        let yaml = block.join("\n");
+
+        let mapping: Mapping = if yaml.trim().is_empty() {
+            Mapping::new()
+        } else {
+            serde_yaml_ng::from_str(&yaml).map_err(|e| {
+                let (line, column) = e
+                    .location()
+                    .map(|location| (location.line(), location.column()))
+                    .unwrap_or((1, 1));
+                // the block starts after the opening deliminator
+                parse_error_at(line + 1, column, e.to_string())
+            })?
+        };
+
+        // semantic errors are reported on the line of the offending key
+        let error_at_key = |key: &str, message: &str| {
+            let line = block
+                .iter()
+                .position(|line| line.starts_with(&format!("{key}:")))
+                .map(|idx| idx + 2)
+                .unwrap_or(1);
+            parse_error_at(line, 1, message)
+        };
+
+        let title = Self::yaml_title(mapping.get("title"))
+            .ok_or_else(|| error_at_key("title", "title has to be a single value"))?;
+
+        let created_at = match mapping.get("date") {
+            None | Some(Value::Null) => fallback_date(),
+            Some(value) => Self::yaml_date(value)
+                .ok_or_else(|| error_at_key("date", "date isn't a date we can read"))?,
+        };
+
+        let tag_strings = Self::yaml_tags(mapping.get("tags"))
+            .ok_or_else(|| error_at_key("tags", "tags have to be a list or a string"))?;
+
+        let aliases = Self::yaml_list(mapping.get("aliases"), false)
+            .ok_or_else(|| error_at_key("aliases", "aliases have to be a list or a string"))?;
+
+        let mut properties = mapping;
+        for key in YAML_KEYS {
+            properties.shift_remove(key);
+        }
+
+        let mut front_matter = FrontMatter::new(title, created_at, tag_strings)
+            .with_aliases(aliases)
+            .with_format(FrontMatterFormat::Yaml);
+        front_matter.properties = Properties::from_yaml(&properties);
+        front_matter.yaml_source = Some(yaml);
+
+        Ok(front_matter)
+    }
+
+    fn yaml_title(value: Option<&Value>) -> Option<String> {
+        match value {
+            None | Some(Value::Null) => Some(String::new()),
+            Some(value) => Self::yaml_scalar(value),
+        }
+    }
+
+    fn yaml_date(value: &Value) -> Option<NaiveDateTime> {
+        Self::yaml_scalar(value)
+            .as_deref()
+            .and_then(Self::parse_yaml_date)
+    }
+
+    fn yaml_tags(value: Option<&Value>) -> Option<Vec<String>> {
+        let tags = Self::yaml_list(value, true)?
+            .into_iter()
+            // other tools write tags as `#tag`
+            .map(|tag| tag.trim_start_matches('#').to_owned())
+            .collect::<Vec<_>>();
+        Some(Self::normalize_tags(tags))
+    }
+
+    fn yaml_scalar(value: &Value) -> Option<String> {
+        match value {
+            Value::String(string) => Some(string.trim().to_owned()),
+            Value::Number(number) => Some(number.to_string()),
+            Value::Bool(bool) => Some(bool.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Reads either a yaml list of scalars or a single string of comma separated values, which can
+    /// also be separated by whitespace if `split_whitespace` is set.
+    fn yaml_list(value: Option<&Value>, split_whitespace: bool) -> Option<Vec<String>> {
+        match value {
+            None | Some(Value::Null) => Some(vec![]),
+            Some(Value::Sequence(values)) => values.iter().map(Self::yaml_scalar).collect(),
+            Some(Value::String(string)) => Some(
+                string
+                    .split(|c: char| c == ',' || (split_whitespace && c.is_whitespace()))
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+                    .map(ToOwned::to_owned)
+                    .collect(),
+            ),
+            Some(value) => Self::yaml_scalar(value).map(|scalar| vec![scalar]),
+        }
+    }
+
+    fn parse_yaml_date(date_str: &str) -> Option<NaiveDateTime> {
+        if let Ok(date) = DateTime::parse_from_rfc3339(date_str) {
+            return Some(date.naive_local());
+        }
 
-        let remaining = lines[5..].join("\n");
+        YAML_DATE_FMT_STRS
+            .iter()
+            .find_map(|fmt| NaiveDateTime::parse_from_str(date_str, fmt).ok())
+            .or_else(|| {
+                NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+            })
+    }
 
-        Ok((FrontMatter::new(title, created_at, tag_strings), remaining))
+    fn to_yaml(&self) -> Result<String, serde_yaml_ng::Error> {
+        let mut mapping = Mapping::new();
+        mapping.insert("title".into(), self.title.clone().into());
+        mapping.insert(
+            "date".into(),
+            self.created_at.format(YAML_DATE_FMT_STR).to_string().into(),
+        );
+        mapping.insert("tags".into(), self.tag_strings.clone().into());
+        if !self.aliases.is_empty() {
+            mapping.insert("aliases".into(), self.aliases.clone().into());
+        }
+        mapping.extend(self.properties.to_yaml());
+
+        match self
+            .yaml_source
+            .as_deref()
+            .and_then(|source| self.patch_yaml(source, mapping.clone()))
+        {
+            Some(yaml) => Ok(yaml),
+            None => serde_yaml_ng::to_string(&mapping),
+        }
+    }
+
+    /// Writes `mapping` as the yaml `source` it was read from, only rewriting the keys whose
+    /// value changed since. Keys that are new are added at the end.
+    ///
+    /// Returns `None` if `source` can't be split into its keys.
+    fn patch_yaml(&self, source: &str, mut mapping: Mapping) -> Option<String> {
+        let mut yaml = String::new();
+        let mut had_date = false;
+
+        for (is_key, text) in Self::split_yaml(source) {
+            if !is_key {
+                // comments and blank lines between the keys
+                yaml.push_str(&text);
+                continue;
+            }
+
+            let entry: Mapping = serde_yaml_ng::from_str(&text).ok()?;
+            let [(key, value)] = entry.iter().collect::<Vec<_>>()[..] else {
+                return None;
+            };
+            let key = yaml_key(key);
+            had_date |= key == "date";
+
+            let current = mapping.shift_remove(key.as_str());
+            if self.says_the_same(&key, value) {
+                yaml.push_str(&text);
+            } else if let Some(current) = current {
+                yaml.push_str(
+                    &serde_yaml_ng::to_string(&Mapping::from_iter([(key.into(), current)])).ok()?,
+                );
+            }
+        }
+
+        // a missing date is the time the file was modified, that doesn't need to be written
+        if !had_date {
+            mapping.shift_remove("date");
+        }
+        if self.title.is_empty() {
+            mapping.shift_remove("title");
+        }
+        if self.tag_strings.is_empty() {
+            mapping.shift_remove("tags");
+        }
+        if !mapping.is_empty() {
+            yaml.push_str(&serde_yaml_ng::to_string(&mapping).ok()?);
+        }
+
+        Some(yaml)
+    }
+
+    /// Splits `source` into the text of each top level key, which includes the lines of nested
+    /// values. Comments and blank lines in between are kept apart, marked as not being a key.
+    fn split_yaml(source: &str) -> Vec<(bool, String)> {
+        let mut parts: Vec<(bool, String)> = vec![];
+        let mut pending = String::new();
+
+        for line in source.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || line.starts_with('#') {
+                pending.push_str(line);
+                pending.push('\n');
+                continue;
+            }
+
+            let starts_key = !line.starts_with(|c: char| c.is_whitespace() || c == '-');
+            match parts.last_mut() {
+                Some((true, text)) if !starts_key => {
+                    // a comment inside of a nested value belongs to it
+                    text.push_str(&pending);
+                    text.push_str(line);
+                    text.push('\n');
+                    pending.clear();
+                }
+                _ => {
+                    if !pending.is_empty() {
+                        parts.push((false, std::mem::take(&mut pending)));
+                    }
+                    parts.push((true, format!("{line}\n")));
+                }
+            }
+        }
+        if !pending.is_empty() {
+            parts.push((false, pending));
+        }
+
+        parts
+    }
+
+    /// Whether `value`, read for `key` from yaml, says the same as this front matter does.
+    fn says_the_same(&self, key: &str, value: &Value) -> bool {
+        match key {
+            "title" => Self::yaml_title(Some(value)).as_ref() == Some(&self.title),
+            // without a date the time the file was modified is used
+            "date" => value.is_null() || Self::yaml_date(value) == Some(self.created_at),
+            "tags" => Self::yaml_tags(Some(value)).as_ref() == Some(&self.tag_strings),
+            "aliases" => Self::yaml_list(Some(value), false).as_ref() == Some(&self.aliases),
+            key => self.properties.get(key) == Some(&PropertyValue::from_yaml(value)),
+        }
     }
 }
 
 impl Display for FrontMatter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let date_fmt_items = StrftimeItems::new(DATE_FMT_STR);
+        let fits_legacy = self.aliases.is_empty() && self.properties.is_empty();
+
+        if self.format == FrontMatterFormat::Yaml || !fits_legacy {
+            writeln!(f, "---")?;
+            write!(f, "{}", self.to_yaml().map_err(|_| std::fmt::Error)?)?;
+            return writeln!(f, "---");
+        }
+
+        let date_fmt_items = StrftimeItems::new(&self.date_format);
         writeln!(f, "---")?;
         writeln!(f, "Title: {}", self.title)?;
         writeln!(
@@ -145,7 +537,7 @@ mod tests {
 
     use chrono::NaiveDateTime;
 
-    use crate::{FrontMatter, zettel::frontmatter::DATE_FMT_STR};
+    use crate::{FrontMatter, FrontMatterFormat, PropertyValue, zettel::frontmatter::DATE_FMT_STR};
 
     lazy_static! {
         static ref test_suite: [(&'static str, (FrontMatter, &'static str)); 1] = [(
@@ -176,4 +568,74 @@ Tags: whoa barber
             assert_eq!(extracted_remaining, *remaining);
         }
     }
+
+    #[test]
+    fn test_extract_yaml() {
+        let raw_text = "---\ntitle: Imported\ndate: 2025-01-01\ntags: [\"#rust\", notes]\naliases: other name\nsource: somewhere\n---\ncontent\n";
+
+        let (front_matter, remaining) = FrontMatter::extract_from_str(raw_text).unwrap();
+
+        assert_eq!(front_matter.format, FrontMatterFormat::Yaml);
+        assert_eq!(front_matter.title, "Imported");
+        assert_eq!(
+            front_matter.created_at,
+            NaiveDateTime::parse_from_str("2025-01-01 12:00:00 AM", DATE_FMT_STR).unwrap()
+        );
+        assert_eq!(front_matter.tag_strings, vec!["rust", "notes"]);
+        assert_eq!(front_matter.aliases, vec!["other name"]);
+        assert_eq!(
+            front_matter.properties.get("source"),
+            Some(&PropertyValue::String("somewhere".to_owned()))
+        );
+        assert_eq!(remaining, "content");
+
+        // written back as yaml
+        let (reparsed, _) = FrontMatter::extract_from_str(front_matter.to_string()).unwrap();
+        assert_eq!(reparsed, front_matter);
+    }
+
+    #[test]
+    fn test_properties_round_trip() {
+        let raw_text = "---\ntitle: Props\ndate: 2025-01-01T10:00:00\ntags: []\nstatus: draft\nrating: 4\nweight: 1.5\npublished: false\nreviewed: 2025-02-03\nsource: '[[kdj6yav]]'\nauthors:\n- eric\n- '[[abcdefghij]]'\nnested:\n  b: 1\n  a: 2\nempty: null\n---\n";
+
+        let (front_matter, _) = FrontMatter::extract_from_str(raw_text).unwrap();
+        let props = &front_matter.properties;
+
+        assert_eq!(props.get("rating"), Some(&PropertyValue::Integer(4)));
+        assert_eq!(props.get("weight"), Some(&PropertyValue::Float(1.5)));
+        assert_eq!(props.get("published"), Some(&PropertyValue::Bool(false)));
+        assert!(matches!(
+            props.get("reviewed"),
+            Some(PropertyValue::Date(_))
+        ));
+        assert_eq!(
+            props.get("source"),
+            Some(&PropertyValue::Link("kdj6yav".to_owned()))
+        );
+        assert!(
+            matches!(props.get("authors"), Some(PropertyValue::List(items)) if items.len() == 2)
+        );
+        assert_eq!(props.get("empty"), Some(&PropertyValue::Empty));
+
+        assert_eq!(front_matter.to_string(), raw_text);
+    }
+
+    #[test]
+    fn test_yaml_round_trip() {
+        let raw_text = "---\n# written by hand\ntags:\n  - Rust   # the language\n  - \"#notes\"\n\nstatus: 'draft'\ndate: 2025-01-01 09:30\ntitle: \"Hand written\"\naliases: [one, two]\n---\n";
+
+        let (mut front_matter, _) = FrontMatter::extract_from_str(raw_text).unwrap();
+        assert_eq!(front_matter.to_string(), raw_text);
+
+        // only what changed is written anew, new keys go at the end
+        front_matter.title = "Renamed".to_owned();
+        front_matter.tag_strings.push("new".to_owned());
+        front_matter
+            .properties
+            .insert("rating", PropertyValue::Integer(3));
+        assert_eq!(
+            front_matter.to_string(),
+            "---\n# written by hand\ntags:\n- rust\n- notes\n- new\n\nstatus: 'draft'\ndate: 2025-01-01 09:30\ntitle: Renamed\naliases: [one, two]\nrating: 3\n---\n"
+        );
+    }
 }
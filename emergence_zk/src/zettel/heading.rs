@@ -0,0 +1,93 @@
+use pulldown_cmark::{Event, Parser, Tag as MkTag, TagEnd};
+use serde::{Deserialize, Serialize};
+
+/// A markdown heading inside the content of a zettel.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub struct Heading {
+    /// 1 for `#`, 2 for `##`, ...
+    pub level: u8,
+    pub text: String,
+    /// The text of the heading, in the form it takes inside of a link anchor
+    pub slug: String,
+    /// 1-based line the heading sits on, inside of the file when it comes from a [`crate::Zettel`]
+    /// and inside of the string it was extracted from otherwise
+    pub line: usize,
+}
+
+impl Heading {
+    /// Extracts all the headings out of markdown `content`.
+    pub fn extract_from_str(content: &str) -> Vec<Self> {
+        let mut headings = vec![];
+        let mut current: Option<Self> = None;
+
+        for (event, range) in Parser::new(content).into_offset_iter() {
+            match event {
+                Event::Start(MkTag::Heading { level, .. }) => {
+                    current = Some(Heading {
+                        level: level as u8,
+                        text: String::new(),
+                        slug: String::new(),
+                        line: content[..range.start].matches('\n').count() + 1,
+                    });
+                }
+                Event::Text(text) | Event::Code(text) => {
+                    if let Some(heading) = current.as_mut() {
+                        heading.text.push_str(&text);
+                    }
+                }
+                Event::End(TagEnd::Heading(_)) => {
+                    if let Some(mut heading) = current.take() {
+                        heading.text = heading.text.trim().to_owned();
+                        heading.slug = Self::slugify(&heading.text);
+                        headings.push(heading);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        headings
+    }
+
+    /// Turns heading text or a link anchor into a slug, so `Some Heading!`, `some heading` and
+    /// `some-heading` all end up as `some-heading`.
+    pub fn slugify(text: &str) -> String {
+        let text = text.replace("%20", " ");
+
+        text.trim()
+            .chars()
+            .filter_map(|c| match c {
+                c if c.is_alphanumeric() || c == '_' => Some(c.to_lowercase().to_string()),
+                ' ' | '-' => Some("-".to_owned()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns whether a link `anchor` points at this heading.
+    pub fn matches(&self, anchor: &str) -> bool {
+        self.slug == Self::slugify(anchor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Heading;
+
+    #[test]
+    fn test_extract_and_match() {
+        let content = "intro\n\n# About `eric`\n\ntext\n\n## Link back!\n";
+        let headings = Heading::extract_from_str(content);
+
+        assert_eq!(headings.len(), 2);
+        assert_eq!(headings[0].level, 1);
+        assert_eq!(headings[0].text, "About eric");
+        assert_eq!(headings[0].line, 3);
+        assert_eq!(headings[1].slug, "link-back");
+
+        assert!(headings[1].matches("link back"));
+        assert!(headings[1].matches("Link-Back"));
+        assert!(headings[1].matches("link%20back"));
+        assert!(!headings[1].matches("link"));
+    }
+}
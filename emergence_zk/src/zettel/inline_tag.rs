@@ -0,0 +1,106 @@
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Options, Parser, Tag as MkTag, TagEnd};
+use serde::{Deserialize, Serialize};
+
+use crate::{TAG_SEPARATOR, Tag, is_name_char};
+
+/// A `#tag` written inside the content of a zettel, rather than in its front matter.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct InlineTag {
+    /// The normalised name of the tag, without the `#`
+    pub name: String,
+    /// Byte range of the tag inside the content, including the `#`
+    pub range: Range<usize>,
+    /// 1-based line the tag sits on, inside of the file when it comes from a [`crate::Zettel`]
+    /// and inside of the string it was extracted from otherwise
+    pub line: usize,
+}
+
+impl InlineTag {
+    /// Extracts all the inline tags out of markdown `content`, skipping code, headings and
+    /// links.
+    pub fn extract_from_str(content: &str) -> Vec<Self> {
+        let mut tags = vec![];
+        // code blocks, headings and links we are currently inside of
+        let mut skipping = 0usize;
+
+        for (event, range) in Parser::new_ext(content, Options::ENABLE_WIKILINKS).into_offset_iter()
+        {
+            match event {
+                Event::Start(
+                    MkTag::CodeBlock(_)
+                    | MkTag::Heading { .. }
+                    | MkTag::Link { .. }
+                    | MkTag::Image { .. },
+                ) => skipping += 1,
+                Event::End(
+                    TagEnd::CodeBlock | TagEnd::Heading(_) | TagEnd::Link | TagEnd::Image,
+                ) => skipping = skipping.saturating_sub(1),
+                // escaped characters split the text, so this is only the raw text if they match
+                Event::Text(text)
+                    if skipping == 0 && content.get(range.clone()) == Some(text.as_ref()) =>
+                {
+                    tags.extend(Self::scan(content, range));
+                }
+                _ => {}
+            }
+        }
+
+        tags
+    }
+
+    /// Finds the tags inside of `content[range]`, which must be plain text.
+    fn scan(content: &str, range: Range<usize>) -> Vec<Self> {
+        let mut tags = vec![];
+        let text = &content[range.clone()];
+
+        for (idx, _) in text.match_indices('#') {
+            let start = range.start + idx;
+            let starts_word = content[..start].chars().next_back().is_none_or(|c| {
+                c.is_whitespace() || matches!(c, '(' | '*' | '_' | '~' | '"' | '\'')
+            });
+            if !starts_word {
+                continue;
+            }
+
+            let rest = &content[start + 1..range.end];
+            let len = rest
+                .find(|c: char| !(is_name_char(c) || c == TAG_SEPARATOR))
+                .unwrap_or(rest.len());
+            let raw = rest[..len].trim_end_matches(['-', '/']);
+
+            // `#1` is more likely to be a number than a tag
+            if raw.is_empty() || raw.chars().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+
+            tags.push(Self {
+                name: Tag::normalize(raw),
+                range: start..start + 1 + raw.len(),
+                line: content[..start].matches('\n').count() + 1,
+            });
+        }
+
+        tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::InlineTag;
+
+    #[test]
+    fn test_extract() {
+        let content = "# Heading #not\n\n#\u{c4}rger #日本語\n\n#todo and (#Question) but not#this, #1 or [#link](x)\n\n```\n#code\n```\n\n`#code` then #project/lsp/.\n";
+        let tags = InlineTag::extract_from_str(content);
+
+        let names = tags.iter().map(|tag| tag.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["\u{e4}rger", "日本語", "todo", "question", "project/lsp"]
+        );
+        assert_eq!(&content[tags[3].range.clone()], "#Question");
+        assert_eq!(tags[4].line, 11);
+    }
+}
@@ -1,22 +1,40 @@
+use std::collections::{BTreeMap, HashMap, hash_map::Entry};
 use std::fmt::Display;
 use std::fs::OpenOptions;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use egui_graphs::Node;
-use pulldown_cmark::{Event, Parser, Tag as MkTag};
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter};
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag as MkTag};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, sea_query::Expr,
+};
 use serde::{Deserialize, Serialize};
-use tracing::{error, info};
+use tracing::info;
 
-use crate::{Link, Tag, Workspace, ZettelId, ZkResult, entities};
+use crate::{
+    DanglingLink, DanglingReason, ExternalLink, KastenConfig, Link, Relation, StoredLinks, Tag,
+    TagRegistry, UsedColors, Workspace, ZettelId, ZkResult, entities, search,
+};
 
-use crate::entities::{prelude::*, tag, zettel, zettel_tag};
+use crate::entities::{prelude::*, zettel, zettel_tag};
 
 mod frontmatter;
 pub use frontmatter::*;
 mod builder;
 pub use builder::*;
+mod heading;
+pub use heading::*;
+mod inline_tag;
+pub use inline_tag::*;
+mod stamp;
+pub(crate) use stamp::*;
+mod property;
+pub use property::*;
+mod rewrite;
+pub(crate) use rewrite::*;
+mod split;
+pub use split::*;
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
 pub struct Zettel {
@@ -25,7 +43,14 @@ pub struct Zettel {
     pub front_matter: FrontMatter,
     pub tags: Vec<Tag>,
     pub links: Vec<Link>,
+    /// Links to things inside the kasten that couldn't be resolved
+    pub dangling_links: Vec<DanglingLink>,
+    /// Links to things outside of the kasten
+    pub external_links: Vec<ExternalLink>,
     pub content: String,
+    /// How many lines of the file come before the content, meaning the front matter and its
+    /// deliminators. Every line number of a zettel is relative to the file, not the content.
+    pub content_line_offset: usize,
 }
 
 impl Zettel {
@@ -39,78 +64,325 @@ impl Zettel {
         Self {
             path,
             id,
+            content_line_offset: front_matter.to_string().lines().count(),
             front_matter,
             tags,
             links: vec![],
+            dangling_links: vec![],
+            external_links: vec![],
             content,
         }
     }
 
-    /// uses the id and root to parse out of the root directory
+    /// Looks up where the zettel with the given `id` lives inside the kasten and parses it.
     pub async fn from_id(id: &ZettelId, ws: &Workspace) -> ZkResult<Self> {
-        let mut path = ws.root.clone();
-        path.push(id.as_str());
-
-        Self::from_path(path, ws).await
+        Self::from_path(Self::locate(id, ws).await?, ws).await
     }
 
-    fn links_from_content(src_id: &ZettelId, content: &str, ws: &Workspace) -> ZkResult<Vec<Link>> {
-        let parsed = Parser::new(content);
+    /// Returns the path of the zettel with the given `id`, which can be anywhere below the root
+    /// of the kasten.
+    ///
+    /// Falls back to a file named after the `id` at the root if the db doesn't know the zettel.
+    pub async fn locate(id: &ZettelId, ws: &Workspace) -> ZkResult<PathBuf> {
+        let stored = ZettelEntity::find_by_nanoid(id.as_str())
+            .one(ws.db.as_ref())
+            .await?
+            .and_then(|z| z.path);
 
-        let mut links = vec![];
+        Ok(match stored {
+            Some(path) => ws.root.join(path),
+            None => ws.root.join(format!("{id}.md")),
+        })
+    }
 
-        for event in parsed {
-            if let Event::Start(MkTag::Link { dest_url, .. }) = event {
-                info!("Found dest_url: {dest_url:#?}");
+    /// Turns a link destination that looks like a path (`./abc.md`, `abc`, `../abc.md#heading`)
+    /// into the `ZettelId` of the file it points at, if that file exists.
+    ///
+    /// Paths are relative to `dir`, the directory of the linking zettel, unless they start with
+    /// a `/`, in which case they are relative to the root of the kasten.
+    fn resolve_path_target(dest_url: &str, dir: &Path, ws: &Workspace) -> Option<ZettelId> {
+        // remove "#" and everything after it
+        let without_anchor = dest_url.split('#').next().unwrap_or_default();
+
+        if without_anchor.is_empty() {
+            return None;
+        }
 
-                let dest_path = {
-                    // remove leading "./"
-                    let without_prefix = dest_url.strip_prefix("./").unwrap_or(&dest_url);
+        let (base, relative) = match without_anchor.strip_prefix('/') {
+            Some(from_root) => (ws.root.as_path(), from_root),
+            None => (dir, without_anchor),
+        };
 
-                    // remove "#" and everything after it
-                    let without_anchor = without_prefix.split('#').next().unwrap();
+        // add .md if not present
+        let normalized = if relative.ends_with(".md") {
+            relative.to_string()
+        } else {
+            format!("{relative}.md")
+        };
 
-                    // add .md if not present
-                    let normalized = if without_anchor.ends_with(".md") {
-                        without_anchor.to_string()
-                    } else {
-                        format!("{}.md", without_anchor)
-                    };
+        // simplest way to validate that the path exists
+        let canon_url = base.join(normalized).canonicalize().ok()?;
 
-                    let mut tmp_root = ws.root.clone();
-                    tmp_root.push(normalized);
-                    tmp_root
-                };
-                // simplest way to validate that the path exists
-                let canon_url = match dest_path.canonicalize() {
-                    Ok(canon_url) => canon_url,
-                    Err(_) => {
-                        error!("Link not found!: {dest_path:?}");
-                        continue;
-                    }
-                };
+        ZettelId::try_from(canon_url).ok()
+    }
 
-                let dst_id = ZettelId::try_from(canon_url)?;
+    /// Resolves the target of a wikilink, which is either a path (`[[literature/kdj6yav]]`), a
+    /// `ZettelId` (`[[kdj6yav]]`) or the title of a zettel (`[[Some Title]]`).
+    async fn resolve_wikilink_target(
+        target: &str,
+        dir: &Path,
+        ws: &Workspace,
+    ) -> ZkResult<Result<ZettelId, DanglingReason>> {
+        if let Some(id) = Self::resolve_path_target(target, dir, ws) {
+            return Ok(Ok(id));
+        }
 
-                let link = Link::new(src_id, dst_id);
+        let title = target.split('#').next().unwrap_or_default().trim();
 
-                links.push(link)
-            }
+        // the zettel might live in another directory than the linking one
+        if let Some(zettel) = ZettelEntity::find_by_nanoid(title)
+            .one(ws.db.as_ref())
+            .await?
+        {
+            return Ok(Ok(zettel.nanoid.as_str().into()));
         }
 
-        Ok(links)
+        let candidates = ZettelEntity::find()
+            .filter(Expr::cust_with_values("trim(title) = ?", [title]))
+            .all(ws.db.as_ref())
+            .await?;
+
+        Ok(match candidates.as_slice() {
+            [] => Err(DanglingReason::NotFound),
+            [zettel] => Ok(zettel.nanoid.as_str().into()),
+            _ => Err(DanglingReason::Ambiguous(
+                candidates
+                    .iter()
+                    .map(|z| z.nanoid.as_str().into())
+                    .collect(),
+            )),
+        })
     }
 
+    /// Parses the `Zettel` at `path` and resolves the links inside of it.
     pub async fn from_path(path: impl Into<PathBuf>, ws: &Workspace) -> ZkResult<Self> {
         let path: PathBuf = path.into();
+        let stamp = FileStamp::read(&path, &ws.config)?;
+
+        let mut zettel = Self::from_path_unlinked(path, ws, &UsedColors::default()).await?;
+        zettel.resolve_links(ws).await?;
+
+        stamp.store(&zettel.id, ws).await?;
+        Ok(zettel)
+    }
+
+    /// Builds the `Zettel` at `path` out of the `tags` and `links` the db has cached for it,
+    /// without touching the db.
+    ///
+    /// This is only correct if the file didn't change since the cache was written.
+    pub(crate) fn from_cache(
+        path: impl Into<PathBuf>,
+        tags: Vec<Tag>,
+        links: StoredLinks,
+        config: &KastenConfig,
+    ) -> ZkResult<Self> {
+        let path: PathBuf = path.into();
 
         let id = ZettelId::try_from(path.as_path())?;
 
-        let (front_matter, content) = FrontMatter::extract_from_file(&path)?;
+        let (front_matter, content, content_line_offset) = FrontMatter::read_file(&path, config)?;
+
+        Ok(Zettel {
+            path,
+            id,
+            front_matter,
+            tags,
+            links: links.links,
+            dangling_links: links.dangling_links,
+            external_links: links.external_links,
+            content,
+            content_line_offset,
+        })
+    }
+
+    /// Resolves the links inside of this `Zettel`'s content, replacing any links that were there,
+    /// and stores the resolved ones in the `link` table.
+    ///
+    /// Title based wikilinks are resolved against the `zettel` table, so every zettel they could
+    /// point at should be synced to the db before this is called.
+    pub(crate) async fn resolve_links(&mut self, ws: &Workspace) -> ZkResult<()> {
+        self.links.clear();
+        self.dangling_links.clear();
+        self.external_links.clear();
+
+        let parsed = Parser::new_ext(&self.content, Options::ENABLE_WIKILINKS).into_offset_iter();
+
+        // relative links are resolved against the directory this zettel lives in
+        let dir = self
+            .path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| ws.root.clone());
+
+        // the byte range of the block we are currently inside of, used as context for links
+        let mut block = 0..0;
+
+        // headings of every zettel that is linked to with an anchor, `None` if it can't be read
+        let mut dest_headings: HashMap<ZettelId, Option<Vec<Heading>>> = HashMap::new();
+
+        for (event, range) in parsed {
+            if let Event::Start(
+                MkTag::Paragraph | MkTag::Heading { .. } | MkTag::Item | MkTag::TableCell,
+            ) = event
+            {
+                block = range.clone();
+            }
 
-        let mut zettel_tag_strings = front_matter.tag_strings.clone();
+            let Event::Start(MkTag::Link {
+                link_type,
+                dest_url,
+                title,
+                ..
+            }) = event
+            else {
+                continue;
+            };
 
-        zettel_tag_strings.sort();
+            info!("Found dest_url: {dest_url:#?}");
+
+            let line =
+                self.content_line_offset + self.content[..range.start].matches('\n').count() + 1;
+            let context = self
+                .content
+                .get(block.clone())
+                .unwrap_or_default()
+                .trim()
+                .to_owned();
+
+            let is_wikilink = matches!(link_type, LinkType::WikiLink { .. });
+            let is_external = matches!(link_type, LinkType::Autolink | LinkType::Email)
+                || (!is_wikilink && ExternalLink::is_external(&dest_url));
+
+            if is_external {
+                self.external_links.push(ExternalLink {
+                    source: self.id.clone(),
+                    url: dest_url.into_string(),
+                    line,
+                    context,
+                });
+                continue;
+            }
+
+            // wikilinks carry their relation as a prefix, markdown links as a `rel:` title
+            let (relation, target) = match dest_url.split_once("::") {
+                Some((relation, target)) if is_wikilink => (Some(Relation::from(relation)), target),
+                _ if is_wikilink => (None, dest_url.as_ref()),
+                _ => (Relation::from_link_title(&title), dest_url.as_ref()),
+            };
+
+            let resolved = if is_wikilink {
+                Self::resolve_wikilink_target(target, &dir, ws).await?
+            } else if target.starts_with('#') {
+                // an anchor into this very zettel
+                Ok(self.id.clone())
+            } else {
+                Self::resolve_path_target(target, &dir, ws).ok_or(DanglingReason::NotFound)
+            };
+
+            let dst_id = match resolved {
+                Ok(dst_id) => dst_id,
+                Err(reason) => {
+                    self.dangling_links.push(DanglingLink {
+                        source: self.id.clone(),
+                        target: dest_url.to_string(),
+                        reason,
+                        line,
+                        context,
+                    });
+                    continue;
+                }
+            };
+
+            let anchor = target
+                .split_once('#')
+                .map(|(_, anchor)| anchor)
+                .filter(|anchor| !anchor.is_empty());
+
+            if let Some(anchor) = anchor {
+                let headings = match dest_headings.entry(dst_id.clone()) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        let headings = if dst_id == self.id {
+                            Some(self.headings())
+                        } else {
+                            FrontMatter::extract_from_file_with(
+                                Self::locate(&dst_id, ws).await?,
+                                &ws.config,
+                            )
+                            .ok()
+                            .map(|(_, content)| Heading::extract_from_str(&content))
+                        };
+                        entry.insert(headings)
+                    }
+                };
+
+                // if the destination can't be read we can't tell whether the anchor exists
+                if headings
+                    .as_ref()
+                    .is_some_and(|headings| !headings.iter().any(|h| h.matches(anchor)))
+                {
+                    self.dangling_links.push(DanglingLink {
+                        source: self.id.clone(),
+                        target: dest_url.to_string(),
+                        reason: DanglingReason::UnknownAnchor(anchor.to_owned()),
+                        line,
+                        context: context.clone(),
+                    });
+                }
+            }
+
+            let link = Link::new(&self.id, dst_id)
+                .with_anchor(anchor)
+                .with_relation(relation)
+                .with_position(line, context);
+
+            self.links.push(link)
+        }
+
+        StoredLinks::store(self, ws).await
+    }
+
+    /// Parses the `Zettel` at `path` and syncs its title and tags to the db, without resolving
+    /// any of its links.
+    ///
+    /// New tags pick their colors out of `used_colors`.
+    pub(crate) async fn from_path_unlinked(
+        path: impl Into<PathBuf>,
+        ws: &Workspace,
+        used_colors: &UsedColors,
+    ) -> ZkResult<Self> {
+        let path: PathBuf = path.into();
+
+        let id = ZettelId::try_from(path.as_path())?;
+
+        let (front_matter, content, content_line_offset) =
+            FrontMatter::read_file(&path, &ws.config)?;
+
+        let relative_path = ws.relative_path(&path).to_string_lossy().into_owned();
+
+        // every tag the zettel uses and whether it only uses it inline, front matter tags win
+        let mut wanted_tags = InlineTag::extract_from_str(&content)
+            .into_iter()
+            .map(|tag| (tag.name, true))
+            .collect::<BTreeMap<_, _>>();
+        wanted_tags.extend(
+            front_matter
+                .tag_strings
+                .iter()
+                .map(|tag| (tag.clone(), false)),
+        );
+        // invalid names are reported as diagnostics instead
+        wanted_tags.retain(|tag, _| Tag::parse_name(tag).is_ok());
 
         let mut zettel_tags = vec![];
 
@@ -128,6 +400,7 @@ impl Zettel {
             let am = entities::zettel::ActiveModel {
                 nanoid: sea_orm::ActiveValue::Set(id.to_string()),
                 title: sea_orm::ActiveValue::Set(front_matter.title.clone()),
+                path: sea_orm::ActiveValue::Set(Some(relative_path.clone())),
                 ..Default::default()
             };
 
@@ -141,41 +414,45 @@ impl Zettel {
                 .expect("we just inserted the zettel")
         };
 
+        let mut stored_links = ZettelTag::find()
+            .filter(zettel_tag::Column::ZettelNanoId.eq(id.as_str()))
+            .all(ws.db.as_ref())
+            .await?
+            .into_iter()
+            .map(|link| (link.tag_nano_id.clone(), link))
+            .collect::<HashMap<_, _>>();
+
         for db_tag in db_zettel.tags.into_iter() {
-            if let Ok(idx) = zettel_tag_strings.binary_search(&db_tag.name) {
+            let Some(link) = stored_links.remove(&db_tag.nanoid) else {
+                continue;
+            };
+
+            if let Some(inline) = wanted_tags.remove(&db_tag.name) {
                 // we remove tags we have already processed
-                zettel_tag_strings.remove(idx);
+                if link.inline != inline {
+                    let mut am = link.into_active_model();
+                    am.inline = sea_orm::ActiveValue::Set(inline);
+                    am.update(ws.db.as_ref()).await?;
+                }
                 zettel_tags.push(Tag::from(db_tag))
             } else {
                 // the db says the file has tag `x`, but that tag is missing from the
-                // front matter, we can assume its gone, lets delete that link
-                let x = ZettelTag::find()
-                    .filter(zettel_tag::Column::ZettelNanoId.eq(id.as_str()))
-                    .filter(zettel_tag::Column::TagNanoId.eq(db_tag.nanoid))
-                    .one(ws.db.as_ref())
-                    .await?
-                    .expect("this link must exist");
-
-                x.into_active_model().delete(ws.db.as_ref()).await?;
+                // zettel, we can assume its gone, lets delete that link
+                link.into_active_model().delete(ws.db.as_ref()).await?;
             }
         }
 
-        // now any tags that are left inside zettel_tag_strings,
+        // now any tags that are left inside wanted_tags,
         // we have to put them inside the db
-        for new_tag in zettel_tag_strings {
-            let am = tag::ActiveModel {
-                nanoid: sea_orm::ActiveValue::Set(ZettelId::default().to_string()),
-                name: sea_orm::ActiveValue::Set(new_tag),
-                color: sea_orm::ActiveValue::Set("random".to_owned()),
-
-                ..Default::default()
-            };
-
-            let x = am.insert(ws.db.as_ref()).await?;
+        let registry = TagRegistry::new(ws).with_used_colors(used_colors);
+        for (new_tag, inline) in wanted_tags {
+            // this also creates any ancestors of hierarchical tags
+            let x = registry.resolve_row(&new_tag).await?;
 
             let am = zettel_tag::ActiveModel {
                 zettel_nano_id: sea_orm::ActiveValue::Set(id.to_string()),
                 tag_nano_id: sea_orm::ActiveValue::Set(x.nanoid.clone()),
+                inline: sea_orm::ActiveValue::Set(inline),
             };
 
             let _ = am.insert(ws.db.as_ref()).await?;
@@ -183,28 +460,60 @@ impl Zettel {
             zettel_tags.push(Tag::from(x));
         }
 
-        if front_matter.title != db_zettel.title {
+        if front_matter.title != db_zettel.title || db_zettel.path.as_ref() != Some(&relative_path)
+        {
             let am = zettel::ActiveModel {
                 id: sea_orm::ActiveValue::Unchanged(db_zettel.id),
                 title: sea_orm::ActiveValue::Set(front_matter.title.clone()),
+                path: sea_orm::ActiveValue::Set(Some(relative_path)),
                 ..Default::default()
             };
 
             am.update(ws.db.as_ref()).await?;
         }
 
-        // now we can do links
-        //
-        let links = Self::links_from_content(&id, &content, ws)?;
-
-        Ok(Zettel {
+        let zettel = Zettel {
             path,
             id,
             front_matter,
             tags: zettel_tags,
             content,
-            links,
-        })
+            content_line_offset,
+            links: vec![],
+            dangling_links: vec![],
+            external_links: vec![],
+        };
+
+        zettel.front_matter.properties.store(&zettel.id, ws).await?;
+        search::index_zettel(&zettel, ws).await?;
+
+        Ok(zettel)
+    }
+
+    /// Removes everything the db knows about the zettel with the given `id`, for when its file is
+    /// gone.
+    pub(crate) async fn remove_from_db(id: &ZettelId, ws: &Workspace) -> ZkResult<()> {
+        LinkEntity::delete_many()
+            .filter(entities::link::Column::SourceNanoId.eq(id.as_str()))
+            .exec(ws.db.as_ref())
+            .await?;
+
+        ZettelTag::delete_many()
+            .filter(zettel_tag::Column::ZettelNanoId.eq(id.as_str()))
+            .exec(ws.db.as_ref())
+            .await?;
+
+        ZettelPropertyEntity::delete_many()
+            .filter(entities::zettel_property::Column::ZettelNanoId.eq(id.as_str()))
+            .exec(ws.db.as_ref())
+            .await?;
+
+        ZettelEntity::delete_many()
+            .filter(zettel::Column::Nanoid.eq(id.as_str()))
+            .exec(ws.db.as_ref())
+            .await?;
+
+        search::remove_zettel(id, ws).await
     }
 
     /// Writes this Zettel to Disk
@@ -219,8 +528,41 @@ impl Zettel {
         Ok(())
     }
 
+    /// Returns the headings inside of this `Zettel`'s content, with lines relative to the file.
+    pub fn headings(&self) -> Vec<Heading> {
+        let mut headings = Heading::extract_from_str(&self.content);
+        for heading in &mut headings {
+            heading.line += self.content_line_offset;
+        }
+        headings
+    }
+
+    /// Returns the `#tags` inside of this `Zettel`'s content, with lines relative to the file.
+    ///
+    /// Their byte ranges stay relative to the content.
+    pub fn inline_tags(&self) -> Vec<InlineTag> {
+        let mut tags = InlineTag::extract_from_str(&self.content);
+        for tag in &mut tags {
+            tag.line += self.content_line_offset;
+        }
+        tags
+    }
+
+    /// The tag this `Zettel` is colored by in the graph: the first one of its front matter, or
+    /// else the first of its inline ones.
+    pub fn primary_tag(&self) -> Option<&Tag> {
+        self.front_matter
+            .tag_strings
+            .iter()
+            .find_map(|name| self.tags.iter().find(|tag| tag.name == *name))
+            .or_else(|| self.tags.first())
+    }
+
     pub fn apply_node_transform(&self, node: &mut Node<Zettel, Link>) {
         node.set_label(self.front_matter.title.to_owned());
+        if let Some(tag) = self.primary_tag() {
+            node.set_color(tag.color.into());
+        }
         let disp = node.display_mut();
         disp.radius = 100.0;
     }
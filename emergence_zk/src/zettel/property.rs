@@ -0,0 +1,365 @@
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter, QuerySelect};
+use serde::{Deserialize, Serialize};
+use serde_yaml_ng::{Mapping, Value};
+
+use crate::{
+    Workspace, ZettelId, ZkError, ZkResult,
+    entities::{prelude::*, zettel_property},
+};
+
+const DATE_FMT_STR: &str = "%Y-%m-%d";
+const DATE_TIME_FMT_STR: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// The value of a custom property inside the front matter of a zettel.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PropertyValue {
+    /// A key without a value, like `source:`
+    Empty,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+    /// A wikilink like `"[[kdj6yav]]"`, holding what is between the brackets
+    Link(String),
+    List(Vec<PropertyValue>),
+    Map(Properties),
+}
+
+impl PropertyValue {
+    pub fn from_yaml(value: &Value) -> Self {
+        match value {
+            Value::Null => Self::Empty,
+            Value::Bool(bool) => Self::Bool(*bool),
+            Value::Number(number) => match number.as_i64() {
+                Some(integer) => Self::Integer(integer),
+                None => Self::Float(number.as_f64().unwrap_or(f64::NAN)),
+            },
+            Value::String(string) => Self::from_yaml_string(string),
+            Value::Sequence(values) => Self::List(values.iter().map(Self::from_yaml).collect()),
+            Value::Mapping(mapping) => Self::Map(Properties::from_yaml(mapping)),
+            Value::Tagged(tagged) => Self::from_yaml(&tagged.value),
+        }
+    }
+
+    /// Strings are only read as dates if writing them back gives the exact same string.
+    fn from_yaml_string(string: &str) -> Self {
+        if let Some(target) = string
+            .strip_prefix("[[")
+            .and_then(|rest| rest.strip_suffix("]]"))
+        {
+            return Self::Link(target.to_owned());
+        }
+
+        if let Ok(date) = NaiveDate::parse_from_str(string, DATE_FMT_STR)
+            && date.format(DATE_FMT_STR).to_string() == string
+        {
+            return Self::Date(date);
+        }
+
+        if let Ok(date_time) = NaiveDateTime::parse_from_str(string, DATE_TIME_FMT_STR)
+            && date_time.format(DATE_TIME_FMT_STR).to_string() == string
+        {
+            return Self::DateTime(date_time);
+        }
+
+        Self::String(string.to_owned())
+    }
+
+    pub fn to_yaml(&self) -> Value {
+        match self {
+            Self::Empty => Value::Null,
+            Self::Bool(bool) => Value::Bool(*bool),
+            Self::Integer(integer) => Value::Number((*integer).into()),
+            Self::Float(float) => Value::Number((*float).into()),
+            Self::String(string) => Value::String(string.clone()),
+            Self::Date(_) | Self::DateTime(_) | Self::Link(_) => Value::String(self.to_string()),
+            Self::List(values) => Value::Sequence(values.iter().map(Self::to_yaml).collect()),
+            Self::Map(properties) => Value::Mapping(properties.to_yaml()),
+        }
+    }
+
+    /// Name of this kind of value, as stored in the db.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Empty => "empty",
+            Self::Bool(_) => "bool",
+            Self::Integer(_) | Self::Float(_) => "number",
+            Self::String(_) => "string",
+            Self::Date(_) => "date",
+            Self::DateTime(_) => "date-time",
+            Self::Link(_) => "link",
+            Self::List(_) => "list",
+            Self::Map(_) => "map",
+        }
+    }
+
+    /// Where this value sits when sorting values of different kinds.
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Empty => 0,
+            Self::Bool(_) => 1,
+            Self::Integer(_) | Self::Float(_) => 2,
+            Self::String(_) => 3,
+            Self::Date(_) => 4,
+            Self::DateTime(_) => 5,
+            Self::Link(_) => 6,
+            Self::List(_) => 7,
+            Self::Map(_) => 8,
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Integer(integer) => Some(*integer as f64),
+            Self::Float(float) => Some(*float),
+            _ => None,
+        }
+    }
+}
+
+impl Display for PropertyValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => Ok(()),
+            Self::Bool(bool) => write!(f, "{bool}"),
+            Self::Integer(integer) => write!(f, "{integer}"),
+            Self::Float(float) => write!(f, "{float}"),
+            Self::String(string) => write!(f, "{string}"),
+            Self::Date(date) => write!(f, "{}", date.format(DATE_FMT_STR)),
+            Self::DateTime(date_time) => write!(f, "{}", date_time.format(DATE_TIME_FMT_STR)),
+            Self::Link(target) => write!(f, "[[{target}]]"),
+            Self::List(values) => {
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                Ok(())
+            }
+            Self::Map(properties) => {
+                let yaml =
+                    serde_yaml_ng::to_string(&properties.to_yaml()).map_err(|_| std::fmt::Error)?;
+                write!(f, "{}", yaml.trim_end())
+            }
+        }
+    }
+}
+
+// floats keep `FrontMatter` from deriving these, so numbers are compared by `f64::total_cmp`
+impl Ord for PropertyValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Bool(a), Self::Bool(b)) => a.cmp(b),
+            (Self::Integer(a), Self::Integer(b)) => a.cmp(b),
+            (Self::String(a), Self::String(b)) | (Self::Link(a), Self::Link(b)) => a.cmp(b),
+            (Self::Date(a), Self::Date(b)) => a.cmp(b),
+            (Self::DateTime(a), Self::DateTime(b)) => a.cmp(b),
+            (Self::List(a), Self::List(b)) => a.cmp(b),
+            (Self::Map(a), Self::Map(b)) => a.cmp(b),
+            (a, b) => match (a.as_f64(), b.as_f64()) {
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                _ => a.rank().cmp(&b.rank()),
+            },
+        }
+    }
+}
+
+impl PartialOrd for PropertyValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for PropertyValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PropertyValue {}
+
+/// Custom properties inside the front matter of a zettel, in the order they were written in.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Properties(Vec<(String, PropertyValue)>);
+
+impl Properties {
+    pub fn from_yaml(mapping: &Mapping) -> Self {
+        Self(
+            mapping
+                .iter()
+                .map(|(key, value)| (yaml_key(key), PropertyValue::from_yaml(value)))
+                .collect(),
+        )
+    }
+
+    pub fn to_yaml(&self) -> Mapping {
+        self.0
+            .iter()
+            .map(|(key, value)| (Value::String(key.clone()), value.to_yaml()))
+            .collect()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&PropertyValue> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Sets `key` to `value`, keeping its place if it was already there, returning the old value.
+    pub fn insert(
+        &mut self,
+        key: impl Into<String>,
+        value: PropertyValue,
+    ) -> Option<PropertyValue> {
+        let key = key.into();
+        match self.0.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, old)) => Some(std::mem::replace(old, value)),
+            None => {
+                self.0.push((key, value));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<PropertyValue> {
+        let idx = self.0.iter().position(|(k, _)| k == key)?;
+        Some(self.0.remove(idx).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &PropertyValue)> {
+        self.0.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Replaces the properties stored in the db for the zettel with the given `id` with these.
+    ///
+    /// Lists are stored as one row per item, so filters match any of them.
+    pub(crate) async fn store(&self, id: &ZettelId, ws: &Workspace) -> ZkResult<()> {
+        ZettelPropertyEntity::delete_many()
+            .filter(zettel_property::Column::ZettelNanoId.eq(id.as_str()))
+            .exec(ws.db.as_ref())
+            .await?;
+
+        let rows = self
+            .iter()
+            .flat_map(|(key, value)| {
+                let items = match value {
+                    PropertyValue::List(items) => items.iter().collect(),
+                    value => vec![value],
+                };
+
+                items
+                    .into_iter()
+                    .map(move |item| zettel_property::ActiveModel {
+                        zettel_nano_id: Set(id.to_string()),
+                        key: Set(key.to_owned()),
+                        value: Set(match item {
+                            PropertyValue::Empty => None,
+                            // links are matched on their target, without the brackets
+                            PropertyValue::Link(target) => Some(target.clone()),
+                            item => Some(item.to_string()),
+                        }),
+                        kind: Set(item.kind().to_owned()),
+                        ..Default::default()
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        ZettelPropertyEntity::insert_many(rows)
+            .exec(ws.db.as_ref())
+            .await?;
+
+        Ok(())
+    }
+}
+
+pub(crate) fn yaml_key(key: &Value) -> String {
+    match key {
+        Value::String(key) => key.clone(),
+        key => serde_yaml_ng::to_string(key)
+            .map(|key| key.trim_end().to_owned())
+            .unwrap_or_default(),
+    }
+}
+
+/// A condition on the custom properties of a zettel, written like `status = draft` or
+/// `source set`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PropertyFilter {
+    /// The property, or any item of it if it is a list, is exactly `value`
+    Equals { key: String, value: String },
+    /// The property is there and has a value
+    IsSet(String),
+}
+
+impl PropertyFilter {
+    /// Returns the ids of every zettel in the db whose properties match this filter.
+    pub async fn matching(&self, ws: &Workspace) -> ZkResult<Vec<ZettelId>> {
+        let query = match self {
+            PropertyFilter::Equals { key, value } => ZettelPropertyEntity::find()
+                .filter(zettel_property::Column::Key.eq(key.as_str()))
+                .filter(zettel_property::Column::Value.eq(value.as_str())),
+            PropertyFilter::IsSet(key) => ZettelPropertyEntity::find()
+                .filter(zettel_property::Column::Key.eq(key.as_str()))
+                .filter(zettel_property::Column::Kind.ne(PropertyValue::Empty.kind())),
+        };
+
+        let ids = query
+            .select_only()
+            .column(zettel_property::Column::ZettelNanoId)
+            .distinct()
+            .into_tuple::<String>()
+            .all(ws.db.as_ref())
+            .await?;
+
+        Ok(ids.iter().map(|id| ZettelId::from(id.as_str())).collect())
+    }
+}
+
+impl FromStr for PropertyFilter {
+    type Err = ZkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((key, value)) = s.split_once('=') {
+            let (key, value) = (key.trim(), value.trim());
+            if key.is_empty() {
+                return Err(ZkError::ParseError(format!("Filter {s:?} has no key")));
+            }
+
+            return Ok(PropertyFilter::Equals {
+                key: key.to_owned(),
+                value: value.to_owned(),
+            });
+        }
+
+        match s.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [key, "set"] => Ok(PropertyFilter::IsSet((*key).to_owned())),
+            _ => Err(ZkError::ParseError(format!(
+                "Filter {s:?} is neither `key = value` nor `key set`"
+            ))),
+        }
+    }
+}
+
+impl Display for PropertyFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PropertyFilter::Equals { key, value } => write!(f, "{key} = {value}"),
+            PropertyFilter::IsSet(key) => write!(f, "{key} set"),
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use std::ops::Range;
+use std::path::{Component, Path};
+
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag as MkTag, TagEnd};
+
+use crate::{Workspace, Zettel, ZettelId, ZkResult};
+
+/// A link inside the content of a zettel, with the byte ranges of the parts that can be rewritten.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) struct LinkSpan {
+    pub is_wikilink: bool,
+    /// The title, id or path the link points at, without its relation and anchor. `None` if it
+    /// doesn't appear inside the link itself, like for reference links.
+    pub target: Option<Range<usize>>,
+    /// The whole destination of the link, the target along with its anchor
+    pub dest: Option<Range<usize>>,
+    /// The part after the `#` in the destination
+    pub anchor: Option<String>,
+    /// The text of the link, `None` if it's the target itself or isn't plain text
+    pub text: Option<Range<usize>>,
+    /// 1-based line inside of the file the link starts on
+    pub line: usize,
+}
+
+/// A replacement of a byte range inside of some content.
+pub(crate) type Edit = (Range<usize>, String);
+
+/// Returns how a markdown link inside of `dir` spells the path to `dest`, both being relative to
+/// the root of the kasten, like `../projects/abc.md`.
+fn relative_link(dir: &Path, dest: &Path) -> String {
+    let dir = dir.components().collect::<Vec<_>>();
+    let dest = dest.components().collect::<Vec<_>>();
+    let common = dir
+        .iter()
+        .zip(&dest)
+        .take_while(|(dir, dest)| dir == dest)
+        .count();
+
+    let ups = dir[common..].iter().map(|_| "..".to_owned());
+    let downs = dest[common..]
+        .iter()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        });
+
+    ups.chain(downs).collect::<Vec<_>>().join("/")
+}
+
+/// Applies `edits` to `content`, the ranges of the edits must not overlap.
+pub(crate) fn apply_edits(content: &str, mut edits: Vec<Edit>) -> String {
+    // back to front, so the ranges of the remaining edits stay valid
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+
+    let mut content = content.to_owned();
+    for (range, replacement) in edits {
+        content.replace_range(range, &replacement);
+    }
+    content
+}
+
+impl Zettel {
+    /// Returns every link inside of this zettel's content that points at the zettel `dest`.
+    ///
+    /// Links are resolved the same way as in [`Zettel::resolve_links`], so title based wikilinks
+    /// are resolved against the titles the db currently has.
+    pub(crate) async fn link_spans_to(
+        &self,
+        dest: &ZettelId,
+        ws: &Workspace,
+    ) -> ZkResult<Vec<LinkSpan>> {
+        let dir = self
+            .path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| ws.root.clone());
+
+        let mut spans = vec![];
+        // the link we are currently inside of, along with the ranges of its text
+        let mut current: Option<(LinkSpan, Vec<Range<usize>>, bool)> = None;
+
+        for (event, range) in
+            Parser::new_ext(&self.content, Options::ENABLE_WIKILINKS).into_offset_iter()
+        {
+            match event {
+                Event::Start(MkTag::Link {
+                    link_type,
+                    dest_url,
+                    ..
+                }) => {
+                    let is_wikilink = matches!(link_type, LinkType::WikiLink { .. });
+                    let has_pothole = matches!(link_type, LinkType::WikiLink { has_pothole: true });
+                    let raw = &self.content[range.clone()];
+
+                    let (dest_start, target, points_at_dest) = if is_wikilink {
+                        // `[[relation::target#anchor|text]]`
+                        let target = dest_url
+                            .split_once("::")
+                            .map_or(dest_url.as_ref(), |(_, target)| target);
+                        let resolved = Self::resolve_wikilink_target(target, &dir, ws).await?;
+
+                        let start = raw
+                            .strip_prefix("[[")
+                            .filter(|inner| inner.starts_with(dest_url.as_ref()))
+                            .map(|_| range.start + 2 + (dest_url.len() - target.len()));
+                        (
+                            start,
+                            start.map(|start| Self::trimmed_target(target, start)),
+                            resolved.as_ref() == Ok(dest),
+                        )
+                    } else {
+                        let resolved = if dest_url.starts_with('#') {
+                            Some(self.id.clone())
+                        } else {
+                            Self::resolve_path_target(&dest_url, &dir, ws)
+                        };
+
+                        // the destination comes after the text
+                        let path = dest_url.split('#').next().unwrap_or_default();
+                        let start = raw
+                            .rfind(dest_url.as_ref())
+                            .filter(|_| !dest_url.is_empty())
+                            .map(|idx| range.start + idx);
+                        (
+                            start,
+                            start
+                                .filter(|_| !path.is_empty())
+                                .map(|start| start..start + path.len()),
+                            resolved.as_ref() == Some(dest),
+                        )
+                    };
+
+                    let dest_range = dest_start.map(|start| {
+                        // wikilinks end at their text, markdown links at their destination
+                        let len = if is_wikilink {
+                            dest_url
+                                .split_once("::")
+                                .map_or(dest_url.len(), |(_, target)| target.len())
+                        } else {
+                            dest_url.len()
+                        };
+                        start..start + len
+                    });
+
+                    let span = LinkSpan {
+                        is_wikilink,
+                        target,
+                        dest: dest_range,
+                        anchor: dest_url
+                            .split_once('#')
+                            .map(|(_, anchor)| anchor.to_owned())
+                            .filter(|anchor| !anchor.is_empty()),
+                        text: None,
+                        line: self.content_line_offset
+                            + self.content[..range.start].matches('\n').count()
+                            + 1,
+                    };
+                    // plain wikilinks show their target as text
+                    let has_text = !is_wikilink || has_pothole;
+                    current = points_at_dest.then_some((span, vec![], has_text));
+                }
+                Event::Text(_) => {
+                    if let Some((_, texts, _)) = current.as_mut() {
+                        texts.push(range);
+                    }
+                }
+                Event::End(TagEnd::Link) => {
+                    if let Some((mut span, texts, has_text)) = current.take() {
+                        if let [text] = texts.as_slice()
+                            && has_text
+                        {
+                            span.text = Some(text.clone());
+                        }
+                        spans.push(span);
+                    }
+                }
+                // anything else inside of the text, like emphasis, means it isn't plain text
+                _ => {
+                    if let Some((_, texts, _)) = current.as_mut()
+                        && !matches!(event, Event::End(_))
+                    {
+                        texts.push(0..0);
+                    }
+                }
+            }
+        }
+
+        Ok(spans)
+    }
+
+    /// Returns the range of the title or id inside of a wikilink `target` starting at `start`,
+    /// without its anchor and surrounding whitespace.
+    fn trimmed_target(target: &str, start: usize) -> Range<usize> {
+        let without_anchor = target.split('#').next().unwrap_or_default();
+        let leading = without_anchor.len() - without_anchor.trim_start().len();
+        let start = start + leading;
+        start..start + without_anchor.trim().len()
+    }
+
+    /// Returns the edits that make every link inside of this zettel that refers to the zettel
+    /// `dest` by its `old_title`, either as wikilink target or as link text, use `new_title`.
+    pub(crate) async fn retitle_edits(
+        &self,
+        dest: &ZettelId,
+        old_title: &str,
+        new_title: &str,
+        ws: &Workspace,
+    ) -> ZkResult<Vec<Edit>> {
+        let old_title = old_title.trim();
+        let mut edits = vec![];
+
+        for span in self.link_spans_to(dest, ws).await? {
+            let refers_by_title =
+                |range: &Range<usize>| self.content[range.clone()].trim() == old_title;
+
+            if span.is_wikilink
+                && let Some(target) = span.target.filter(refers_by_title)
+            {
+                edits.push((target, new_title.to_owned()));
+            }
+
+            if let Some(text) = span.text.filter(refers_by_title) {
+                edits.push((text, new_title.to_owned()));
+            }
+        }
+
+        Ok(edits)
+    }
+
+    /// Returns the edits that make every link inside of this zettel that points at `from` point
+    /// at `to` instead. Links referring to `from` by its title, as wikilink target or link text,
+    /// use the title of `to`, other wikilinks its id and markdown links the path to it.
+    ///
+    /// Anchors and relations of the links are kept as they are.
+    pub(crate) async fn redirect_edits(
+        &self,
+        from: &Zettel,
+        to: &Zettel,
+        ws: &Workspace,
+    ) -> ZkResult<Vec<Edit>> {
+        let from_title = from.front_matter.title.trim();
+        let to_title = to.front_matter.title.trim();
+
+        let dir = ws.relative_path(self.path.parent().unwrap_or(&ws.root));
+        let dest = ws.relative_path(&to.path);
+
+        let mut edits = vec![];
+
+        for span in self.link_spans_to(&from.id, ws).await? {
+            let refers_by_title =
+                |range: &Range<usize>| self.content[range.clone()].trim() == from_title;
+
+            if let Some(target) = span.target {
+                let old = &self.content[target.clone()];
+                let new = if span.is_wikilink && refers_by_title(&target) {
+                    to_title.to_owned()
+                } else if span.is_wikilink {
+                    to.id.to_string()
+                } else {
+                    // keep the style the link was written in
+                    let path = if old.starts_with('/') {
+                        format!("/{}", dest.to_string_lossy())
+                    } else {
+                        relative_link(&dir, &dest)
+                    };
+                    if old.ends_with(".md") {
+                        path
+                    } else {
+                        path.trim_end_matches(".md").to_owned()
+                    }
+                };
+                edits.push((target, new));
+            }
+
+            if let Some(text) = span.text.filter(refers_by_title) {
+                edits.push((text, to_title.to_owned()));
+            }
+        }
+
+        Ok(edits)
+    }
+
+    /// Returns the edit that points the link `span` inside of this zettel at `to`, keeping its
+    /// anchor. `None` if the destination of the link can't be found inside of it.
+    pub(crate) fn retarget_edit(
+        &self,
+        span: &LinkSpan,
+        to: &Zettel,
+        ws: &Workspace,
+    ) -> Option<Edit> {
+        let dest = span.dest.clone()?;
+        let anchor = span
+            .anchor
+            .as_ref()
+            .map(|anchor| format!("#{anchor}"))
+            .unwrap_or_default();
+
+        if span.is_wikilink {
+            return Some((dest, format!("{}{anchor}", to.id)));
+        }
+
+        let old = &self.content[dest.clone()];
+        let old_path = old.split('#').next().unwrap_or_default();
+        let to_path = ws.relative_path(&to.path);
+
+        // keep the style the link was written in, anchors into the zettel itself get a full path
+        let path = if old_path.starts_with('/') {
+            format!("/{}", to_path.to_string_lossy())
+        } else {
+            let dir = ws.relative_path(self.path.parent().unwrap_or(&ws.root));
+            relative_link(&dir, &to_path)
+        };
+        let path = if old_path.is_empty() || old_path.ends_with(".md") {
+            path
+        } else {
+            path.trim_end_matches(".md").to_owned()
+        };
+
+        Some((dest, format!("{path}{anchor}")))
+    }
+}
@@ -0,0 +1,77 @@
+use std::ops::{Range, RangeInclusive};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Zettel, ZkError, ZkResult};
+
+/// A part of a zettel that should become a zettel of its own.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Split {
+    /// The heading matching this anchor, along with everything below it up to the next heading
+    /// of the same or a higher level
+    Heading(String),
+    /// These 1-based lines of the file, which have to lie after the front matter
+    Lines(RangeInclusive<usize>),
+}
+
+impl Zettel {
+    /// Returns the 0-based line ranges of the content that `splits` cover, sorted.
+    ///
+    /// # Errors
+    /// Errors if a heading doesn't exist, if lines lie outside of the content or if any of the
+    /// pieces overlap.
+    pub(crate) fn split_ranges(&self, splits: &[Split]) -> ZkResult<Vec<Range<usize>>> {
+        let line_count = self.content.lines().count();
+        let offset = self.content_line_offset;
+        // heading lines are relative to the file, the ranges to the content
+        let headings = self.headings();
+
+        let mut ranges = splits
+            .iter()
+            .map(|split| match split {
+                Split::Heading(anchor) => {
+                    let (idx, heading) = headings
+                        .iter()
+                        .enumerate()
+                        .find(|(_, heading)| heading.matches(anchor))
+                        .ok_or_else(|| {
+                            ZkError::InvalidOperation(format!(
+                                "{} has no heading {anchor:?}",
+                                self.id
+                            ))
+                        })?;
+
+                    let end = headings[idx + 1..]
+                        .iter()
+                        .find(|next| next.level <= heading.level)
+                        .map_or(line_count, |next| next.line - 1 - offset);
+
+                    Ok(heading.line - 1 - offset..end)
+                }
+                Split::Lines(lines) => {
+                    if *lines.start() <= offset
+                        || lines.is_empty()
+                        || *lines.end() > offset + line_count
+                    {
+                        return Err(ZkError::InvalidOperation(format!(
+                            "{} has no lines {}-{}",
+                            self.id,
+                            lines.start(),
+                            lines.end()
+                        )));
+                    }
+                    Ok(lines.start() - 1 - offset..lines.end() - offset)
+                }
+            })
+            .collect::<ZkResult<Vec<_>>>()?;
+
+        ranges.sort_by_key(|range| range.start);
+        if ranges.windows(2).any(|pair| pair[0].end > pair[1].start) {
+            return Err(ZkError::InvalidOperation(
+                "the pieces to split off overlap".to_owned(),
+            ));
+        }
+
+        Ok(ranges)
+    }
+}
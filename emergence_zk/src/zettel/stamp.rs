@@ -0,0 +1,119 @@
+use std::{fs, path::Path, time::UNIX_EPOCH};
+
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, sea_query::Expr};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    KastenConfig, Workspace, ZettelId, ZkResult,
+    entities::{prelude::*, zettel},
+};
+
+/// What a zettel file looked like the last time it was parsed, used to skip re-processing files
+/// that didn't change.
+///
+/// It also remembers the settings of the kasten the file was parsed with, so changing them in
+/// `.emergence/config.toml` re-processes every file.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) struct FileStamp {
+    /// Nanoseconds since the unix epoch
+    pub mtime: i64,
+    /// Hex encoded sha256 of the settings that change how the file is parsed
+    pub settings: String,
+    /// Hex encoded sha256 of the whole file
+    pub hash: String,
+}
+
+/// Returns the hex encoded sha256 of `bytes`.
+fn hex_sha256(bytes: impl AsRef<[u8]>) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+impl FileStamp {
+    /// Returns the stamp stored in a row of the `zettel` table, if the zettel was ever fully parsed.
+    pub fn from_row(
+        mtime: Option<i64>,
+        content_hash: Option<String>,
+        settings_hash: Option<String>,
+    ) -> Option<Self> {
+        Some(Self {
+            mtime: mtime?,
+            settings: settings_hash?,
+            hash: content_hash?,
+        })
+    }
+
+    /// Returns the hash of the parts of `config` that change how a zettel is parsed.
+    fn settings_hash(config: &KastenConfig) -> String {
+        let KastenConfig {
+            date_format,
+            id_length,
+            id_alphabet,
+            ignored_paths,
+            ..
+        } = config;
+
+        hex_sha256(format!(
+            "{date_format:?}{id_length:?}{id_alphabet:?}{ignored_paths:?}"
+        ))
+    }
+
+    /// Reads the stamp of the file at `path`, parsed with the settings of `config`.
+    pub fn read(path: &Path, config: &KastenConfig) -> ZkResult<Self> {
+        Self::read_against(path, config, None)
+    }
+
+    /// Reads the stamp of the file at `path`, reusing the hash of `stored` if the mtime of the
+    /// file and the settings of `config` still match it.
+    pub fn read_against(
+        path: &Path,
+        config: &KastenConfig,
+        stored: Option<&Self>,
+    ) -> ZkResult<Self> {
+        let mtime = fs::metadata(path)?
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as i64)
+            .unwrap_or_default();
+        let settings = Self::settings_hash(config);
+
+        if let Some(stored) = stored
+            && stored.mtime == mtime
+            && stored.settings == settings
+        {
+            return Ok(stored.clone());
+        }
+
+        let hash = hex_sha256(fs::read(path)?);
+
+        Ok(Self {
+            mtime,
+            settings,
+            hash,
+        })
+    }
+
+    /// Returns whether the file this stamp was read from is the same, parsed with the same
+    /// settings, as the one `other` was read from.
+    pub fn matches(&self, other: &Self) -> bool {
+        self.settings == other.settings && self.hash == other.hash
+    }
+
+    /// Stores this stamp for the zettel with the given `id`.
+    pub async fn store(&self, id: &ZettelId, ws: &Workspace) -> ZkResult<()> {
+        ZettelEntity::update_many()
+            .col_expr(zettel::Column::Mtime, Expr::value(self.mtime))
+            .col_expr(zettel::Column::ContentHash, Expr::value(self.hash.clone()))
+            .col_expr(
+                zettel::Column::SettingsHash,
+                Expr::value(self.settings.clone()),
+            )
+            .filter(zettel::Column::Nanoid.eq(id.as_str()))
+            .exec(ws.db.as_ref())
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use emergence_zk::{Kasten, ZettelBuilder, ZettelId};
+
+#[tokio::test]
+async fn analytics_find_orphans_hubs_bridges_and_clusters() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    // two triangles of zettels, joined by a link from `C` to `D`
+    let mut ids = vec![];
+    for (title, content) in [
+        ("A", "[[B]] [[C]]\n"),
+        ("B", "[[C]] [[C]] [[B]]\n"),
+        ("C", "[[A]] [[D]]\n"),
+        ("D", "[[E]] [[F]]\n"),
+        ("E", "[[F]]\n"),
+        ("F", "[[Nobody]]\n"),
+        ("Lonely", "no links at all\n"),
+    ] {
+        let zettel = ZettelBuilder::new(ws)
+            .with_title(title)
+            .with_content(content)
+            .build()
+            .await
+            .expect("able to build zettel");
+        ids.push(zettel.id);
+    }
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    let analytics = kasten.analytics();
+    let ids_of = |notes: Vec<&ZettelId>| notes.into_iter().cloned().collect::<Vec<_>>();
+
+    assert_eq!(analytics.notes.len(), 7);
+    assert_eq!(
+        ids_of(analytics.orphans().map(|note| &note.id).collect()),
+        vec![ids[6].clone()]
+    );
+    assert_eq!(
+        ids_of(analytics.dead_ends().map(|note| &note.id).collect()),
+        vec![ids[5].clone()]
+    );
+
+    // repeated links and links to itself don't count
+    let b = analytics.note(&ids[1]).expect("part of the graph");
+    assert_eq!((b.in_degree, b.out_degree), (1, 1));
+
+    let hubs = analytics.hubs(2);
+    assert!(hubs.iter().all(|note| note.in_degree == 2));
+    let mut hubs = hubs
+        .into_iter()
+        .map(|note| note.id.clone())
+        .collect::<Vec<_>>();
+    hubs.sort();
+    let mut expected = vec![ids[2].clone(), ids[5].clone()];
+    expected.sort();
+    assert_eq!(hubs, expected);
+
+    let total = analytics
+        .notes
+        .iter()
+        .map(|note| note.page_rank)
+        .sum::<f64>();
+    assert!((total - 1.0).abs() < 1e-6);
+
+    let mut bridges = analytics
+        .bridges(2)
+        .into_iter()
+        .map(|note| note.id.clone())
+        .collect::<Vec<_>>();
+    bridges.sort();
+    let mut expected = vec![ids[2].clone(), ids[3].clone()];
+    expected.sort();
+    assert_eq!(bridges, expected);
+
+    assert_eq!(analytics.components.len(), 2);
+    assert_eq!(analytics.components[0].len(), 6);
+    assert_eq!(analytics.components[1], vec![ids[6].clone()]);
+
+    assert_eq!(analytics.communities.len(), 3);
+    for triangle in [&ids[..3], &ids[3..6]] {
+        let mut triangle = triangle.to_vec();
+        triangle.sort();
+        assert!(analytics.communities.contains(&triangle));
+    }
+}
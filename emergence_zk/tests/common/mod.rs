@@ -0,0 +1,24 @@
+// every test binary only uses some of these
+#![allow(dead_code)]
+
+use std::{fs, path::Path};
+
+use emergence_zk::{Kasten, Zettel, ZettelId};
+
+/// Returns the zettel with the given `id` out of the graph of `kasten`.
+pub fn zettel<'a>(kasten: &'a Kasten, id: &ZettelId) -> &'a Zettel {
+    let gid = kasten
+        .zid_to_gid
+        .get(id)
+        .expect("zettel must be in the graph");
+    kasten.graph.node(*gid).expect("node must exist").payload()
+}
+
+/// Writes `tags` into the legacy front matter of the zettel at `path`, which is where tags only
+/// come from.
+pub fn write_tags(path: &Path, tags: &str) {
+    let text = fs::read_to_string(path)
+        .expect("able to read zettel")
+        .replace("Tags: ", &format!("Tags: {tags}"));
+    fs::write(path, text).expect("able to write zettel");
+}
@@ -0,0 +1,94 @@
+use std::fs;
+
+use emergence_zk::{FrontMatter, Kasten, KastenConfig, Workspace, ZettelBuilder, ZkError};
+
+#[tokio::test]
+async fn config_is_read_from_the_kasten() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    // a new kasten gets the defaults written out
+    assert_eq!(
+        KastenConfig::load(dir.path()).expect("defaults are valid"),
+        KastenConfig::default()
+    );
+
+    let legacy = {
+        let ws = Workspace::new(dir.path())
+            .await
+            .expect("able to open workspace");
+        ZettelBuilder::new(&ws)
+            .with_title("Legacy")
+            .build()
+            .await
+            .expect("able to build zettel")
+    };
+
+    fs::write(
+        KastenConfig::path(dir.path()),
+        "date_format = \"%d.%m.%Y %H:%M:%S\"\nid_length = 6\nid_alphabet = \"0123456789\"\nignored_paths = [\"templates\"]\n",
+    )
+    .expect("able to write config");
+
+    let ws = Workspace::new(dir.path())
+        .await
+        .expect("able to open workspace");
+    assert_eq!(ws.config.id_length, 6);
+
+    let zettel = ZettelBuilder::new(&ws)
+        .with_title("Configured")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    assert_eq!(zettel.id.as_str().len(), 6);
+    assert!(zettel.id.as_str().chars().all(|c| c.is_ascii_digit()));
+
+    let date_line = fs::read_to_string(&zettel.path)
+        .expect("able to read zettel")
+        .lines()
+        .nth(2)
+        .expect("front matter has a date")
+        .to_owned();
+    let expected = zettel.front_matter.created_at.format("Date: %d.%m.%Y");
+    assert!(date_line.starts_with(&expected.to_string()), "{date_line}");
+
+    fs::create_dir_all(dir.path().join("templates")).expect("able to create dir");
+    fs::write(
+        dir.path().join("templates/abcdefghij.md"),
+        "---\nTitle: Template\nDate: 2025-01-01 12:00:00 AM\nTags: \n---\n",
+    )
+    .expect("able to write template");
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    // zettels written with the default date format are still read
+    assert!(kasten.zid_to_gid.contains_key(&legacy.id));
+    assert!(kasten.zid_to_gid.contains_key(&zettel.id));
+    assert_eq!(kasten.zid_to_gid.len(), 2);
+    assert!(kasten.diagnostics.is_empty(), "{:?}", kasten.diagnostics);
+
+    let (front_matter, _) =
+        FrontMatter::extract_from_file_with(&zettel.path, &ws.config).expect("still parses");
+    assert_eq!(front_matter.date_format, ws.config.date_format);
+}
+
+#[tokio::test]
+async fn invalid_config_is_rejected() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    fs::write(KastenConfig::path(dir.path()), "id_alphabet = \"a/\"\n")
+        .expect("able to write config");
+
+    let err = Kasten::parse(dir.path())
+        .await
+        .expect_err("config is invalid");
+    assert!(matches!(err, ZkError::ConfigError(_)), "{err:?}");
+}
@@ -0,0 +1,65 @@
+use emergence_zk::{Kasten, Severity, ZettelBuilder};
+
+#[tokio::test]
+async fn broken_zettels_are_reported_with_their_location() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    let fine = ZettelBuilder::new(&kasten.ws)
+        .with_title("Fine")
+        .with_content("see [[Nobody]]\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let bad_date = dir.path().join("baddatezkx.md");
+    std::fs::write(
+        &bad_date,
+        "---\nTitle: Bad\nDate: yesterday\nTags: \n---\ncontent\n",
+    )
+    .expect("able to write file");
+
+    let bad_yaml = dir.path().join("badyamlzkx.md");
+    std::fs::write(&bad_yaml, "---\ntitle: Bad\ntags: [unclosed\n---\n")
+        .expect("able to write file");
+
+    let unclosed = dir.path().join("unclosedzk.md");
+    std::fs::write(&unclosed, "---\ntitle: Never closed\n").expect("able to write file");
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    // the broken ones are reported instead of being dropped silently
+    assert_eq!(kasten.zid_to_gid.len(), 1);
+
+    let find = |path: &std::path::Path| {
+        kasten
+            .diagnostics
+            .iter()
+            .find(|d| d.path == path)
+            .unwrap_or_else(|| panic!("missing diagnostic for {path:?}"))
+    };
+
+    let date = find(&bad_date);
+    assert_eq!(date.severity, Severity::Error);
+    assert_eq!((date.line, date.column), (3, 7));
+
+    let yaml = find(&bad_yaml);
+    assert_eq!(yaml.severity, Severity::Error);
+    // the unclosed list only turns into an error at the end of the front matter
+    assert_eq!(yaml.line, 4);
+    assert!(!yaml.message.is_empty());
+
+    let never_closed = find(&unclosed);
+    assert!(never_closed.message.contains("never closed"));
+
+    let broken_link = find(&fine.path);
+    assert_eq!(broken_link.severity, Severity::Warning);
+    assert_eq!((broken_link.line, broken_link.column), (7, 7));
+    assert!(broken_link.message.contains("Nobody"));
+
+    assert_eq!(kasten.diagnostics.len(), 4);
+}
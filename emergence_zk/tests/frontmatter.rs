@@ -0,0 +1,123 @@
+use emergence_zk::{
+    FrontMatter, FrontMatterFormat, Kasten, PropertyFilter, PropertyValue, ZettelBuilder, ZettelId,
+};
+
+#[tokio::test]
+async fn yaml_and_legacy_zettels_parse_and_keep_their_format() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    let legacy = ZettelBuilder::new(&kasten.ws)
+        .with_title("Legacy")
+        .with_content("[[Imported]]\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let imported = dir.path().join("importedzk.md");
+    std::fs::write(
+        &imported,
+        "---\ntitle: Imported\ndate: 2024-03-01T10:00:00+01:00\ntags:\n  - reading\n  - \"#books\"\n---\n\nsome text\n",
+    )
+    .expect("able to write file");
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    assert_eq!(kasten.zid_to_gid.len(), 2);
+
+    let gid = kasten.zid_to_gid[&"importedzk".into()];
+    let zettel = kasten.graph.node(gid).expect("node must exist").payload();
+    assert_eq!(zettel.front_matter.format, FrontMatterFormat::Yaml);
+    assert_eq!(zettel.front_matter.title, "Imported");
+    assert_eq!(zettel.front_matter.tag_strings, vec!["reading", "books"]);
+
+    let names = zettel
+        .tags
+        .iter()
+        .map(|tag| tag.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["books", "reading"]);
+
+    // the legacy zettel links to the imported one by its title
+    let gid = kasten.zid_to_gid[&legacy.id];
+    let source = kasten.graph.node(gid).expect("node must exist").payload();
+    assert_eq!(source.links.len(), 1);
+
+    zettel.flush().expect("able to write zettel");
+    let (front_matter, _) = FrontMatter::extract_from_file(&imported).expect("still parses");
+    assert_eq!(front_matter, zettel.front_matter);
+
+    source.flush().expect("able to write zettel");
+    let (front_matter, _) = FrontMatter::extract_from_file(&legacy.path).expect("still parses");
+    assert_eq!(front_matter.format, FrontMatterFormat::Legacy);
+}
+
+#[tokio::test]
+async fn properties_are_stored_and_filterable() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    let draft = dir.path().join("draftzettl.md");
+    std::fs::write(
+        &draft,
+        "---\ntitle: Draft\nstatus: draft\nsource: '[[finalzettl]]'\nauthors:\n- ada\n- grace\n---\n",
+    )
+    .expect("able to write file");
+
+    let last = dir.path().join("finalzettl.md");
+    std::fs::write(&last, "---\ntitle: Final\nstatus: final\nsource:\n---\n")
+        .expect("able to write file");
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    let matching = |filter: &str| {
+        let filter: PropertyFilter = filter.parse().expect("valid filter");
+        let kasten = &kasten;
+        async move { kasten.filter(&filter).await.expect("able to filter") }
+    };
+
+    let draft_id = ZettelId::from("draftzettl");
+    let final_id = ZettelId::from("finalzettl");
+
+    assert_eq!(matching("status = draft").await, vec![draft_id.clone()]);
+    assert_eq!(matching("authors = grace").await, vec![draft_id.clone()]);
+    assert_eq!(
+        matching("source = finalzettl").await,
+        vec![draft_id.clone()]
+    );
+    // an empty property isn't set
+    assert_eq!(matching("source set").await, vec![draft_id.clone()]);
+    assert!(matching("status = archived").await.is_empty());
+    assert!("= draft".parse::<PropertyFilter>().is_err());
+
+    // edits replace what was stored
+    let gid = kasten.zid_to_gid[&final_id];
+    let mut zettel = kasten
+        .graph
+        .node(gid)
+        .expect("node exists")
+        .payload()
+        .clone();
+    zettel
+        .front_matter
+        .properties
+        .insert("status", PropertyValue::String("draft".to_owned()));
+    zettel.flush().expect("able to write zettel");
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    let mut drafts = kasten
+        .filter(&"status = draft".parse().expect("valid filter"))
+        .await
+        .expect("able to filter");
+    drafts.sort();
+    assert_eq!(drafts, vec![draft_id, final_id]);
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use emergence_zk::{DanglingReason, Kasten, Zettel, ZettelBuilder, ZettelId};
+
+use common::zettel;
+
+fn dests(zettel: &Zettel) -> Vec<ZettelId> {
+    zettel.links.iter().map(|link| link.dest.clone()).collect()
+}
+
+#[tokio::test]
+async fn subdirectories_are_parsed_and_links_are_relative() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let project = ZettelBuilder::new(ws)
+        .with_title("Project")
+        .with_directory("projects")
+        .with_content("# Goals\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let paper = ZettelBuilder::new(ws)
+        .with_title("Paper")
+        .with_directory("literature/papers")
+        .with_content(format!(
+            "[up](../../projects/{0}.md#goals) [root](/projects/{0}) [here]({0}.md)\n",
+            project.id
+        ))
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let index = ZettelBuilder::new(ws)
+        .with_title("Index")
+        .with_content(format!(
+            "[paper](literature/papers/{}.md) [[{}]] [[Project#goals]]\n",
+            paper.id, project.id
+        ))
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    // hidden directories aren't part of the kasten
+    std::fs::create_dir_all(dir.path().join(".hidden")).expect("able to create dir");
+    std::fs::write(dir.path().join(".hidden/abcdefghij.md"), "not a zettel")
+        .expect("able to write file");
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    assert_eq!(kasten.zid_to_gid.len(), 3);
+
+    let index = zettel(&kasten, &index.id);
+    assert_eq!(
+        dests(index),
+        vec![paper.id.clone(), project.id.clone(), project.id.clone()]
+    );
+    assert!(index.dangling_links.is_empty());
+
+    let paper = zettel(&kasten, &paper.id);
+    assert_eq!(dests(paper), vec![project.id.clone(), project.id.clone()]);
+    assert_eq!(paper.dangling_links.len(), 1);
+    assert_eq!(paper.dangling_links[0].reason, DanglingReason::NotFound);
+
+    let located = Zettel::locate(&project.id, &kasten.ws)
+        .await
+        .expect("able to locate zettel");
+    assert_eq!(
+        located,
+        dir.path().join(format!("projects/{}.md", project.id))
+    );
+}
+
+#[tokio::test]
+async fn moved_zettels_are_picked_up() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let target = ZettelBuilder::new(ws)
+        .with_title("Target")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let source = ZettelBuilder::new(ws)
+        .with_title("Source")
+        .with_content(format!("[a]({0}.md) [[{0}]]\n", target.id))
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    assert_eq!(zettel(&kasten, &source.id).links.len(), 2);
+
+    let moved = dir.path().join(format!("archive/{}.md", target.id));
+    std::fs::create_dir_all(dir.path().join("archive")).expect("able to create dir");
+    std::fs::rename(&target.path, &moved).expect("able to move zettel");
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    // the path based link broke, the id based one still resolves
+    let source = zettel(&kasten, &source.id);
+    assert_eq!(dests(source), vec![target.id.clone()]);
+    assert_eq!(source.dangling_links.len(), 1);
+    assert_eq!(zettel(&kasten, &target.id).path, moved);
+}
@@ -0,0 +1,329 @@
+use std::fs;
+
+use emergence_zk::{DanglingReason, Kasten, Link, Relation, Zettel, ZettelBuilder, ZettelId};
+
+#[tokio::test]
+async fn wikilinks_resolve_by_id_and_title() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let target = ZettelBuilder::new(ws)
+        .with_title("Some Title")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    for _ in 0..2 {
+        ZettelBuilder::new(ws)
+            .with_title("Twin")
+            .build()
+            .await
+            .expect("able to build zettel");
+    }
+
+    let source = ZettelBuilder::new(ws)
+        .with_title("Source")
+        .with_content(format!(
+            "[[{0}]]\n\n[[{0}|custom label]]\n\n[[Some Title]]\n\n[[Twin]]\n\n[[Nobody]]\n",
+            target.id
+        ))
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let parsed = Zettel::from_path(&source.path, ws)
+        .await
+        .expect("able to parse zettel");
+
+    let dests = parsed
+        .links
+        .iter()
+        .map(|link| link.dest.clone())
+        .collect::<Vec<ZettelId>>();
+
+    assert_eq!(dests, vec![target.id.clone(); 3]);
+
+    let reasons = parsed
+        .dangling_links
+        .iter()
+        .map(|link| (link.target.as_str(), &link.reason))
+        .collect::<Vec<_>>();
+
+    assert!(matches!(
+        reasons.as_slice(),
+        [
+            ("Twin", DanglingReason::Ambiguous(candidates)),
+            ("Nobody", DanglingReason::NotFound)
+        ] if candidates.len() == 2
+    ));
+
+    // parsing the whole kasten reports them as well
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    let messages = kasten
+        .diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.path == source.path)
+        .map(|diagnostic| diagnostic.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(messages.len(), 2);
+    assert!(
+        messages[0].starts_with("broken link to Twin: ambiguous between "),
+        "{messages:?}"
+    );
+    assert_eq!(messages[1], "broken link to Nobody: not found");
+}
+
+#[tokio::test]
+async fn links_are_classified() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    // exists on disk, but can't be parsed into a zettel
+    std::fs::write(dir.path().join("broken.md"), "no front matter").expect("able to write");
+
+    let source = ZettelBuilder::new(ws)
+        .with_title("Source")
+        .with_content(
+            "[web](https://example.com)\n\n<https://example.org>\n\n[gone](./missing.md)\n\n[broken](./broken.md)\n",
+        )
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("links to unparsable zettels must not fail parsing");
+
+    let gid = kasten.zid_to_gid[&source.id];
+    let parsed = kasten.graph.node(gid).expect("must exist").payload();
+
+    let urls = parsed
+        .external_links
+        .iter()
+        .map(|link| link.url.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(urls, ["https://example.com", "https://example.org"]);
+
+    let dangling = kasten
+        .dangling_links()
+        .map(|link| link.target.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(dangling, ["./missing.md"]);
+
+    assert_eq!(parsed.links.len(), 1);
+    assert_eq!(kasten.graph.edge_count(), 0);
+}
+
+#[tokio::test]
+async fn backlinks_record_source_and_context() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let target = ZettelBuilder::new(ws)
+        .with_title("Target")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let source = ZettelBuilder::new(ws)
+        .with_title("Source")
+        .with_content(format!("intro\n\nsee [[{}]] for more\n", target.id))
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    let backlinks = kasten.backlinks(&target.id);
+
+    assert_eq!(backlinks.len(), 1);
+    assert_eq!(backlinks[0].source, source.id);
+    assert_eq!(backlinks[0].source_title, "Source");
+    // lines count from the start of the file, front matter included
+    let expected_line = fs::read_to_string(&source.path)
+        .expect("able to read zettel")
+        .lines()
+        .position(|line| line.starts_with("see"))
+        .expect("link line exists")
+        + 1;
+    assert_eq!(backlinks[0].line, expected_line);
+    assert_eq!(
+        backlinks[0].context,
+        format!("see [[{}]] for more", target.id)
+    );
+    assert!(kasten.backlinks(&source.id).is_empty());
+}
+
+#[tokio::test]
+async fn links_are_persisted_in_db() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let target = ZettelBuilder::new(ws)
+        .with_title("Target")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let source = ZettelBuilder::new(ws)
+        .with_title("Source")
+        .with_content(format!("[method](./{}.md#method)\n", target.id))
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let parsed = Zettel::from_path(&source.path, ws)
+        .await
+        .expect("able to parse zettel");
+
+    let outgoing = Link::outgoing(&source.id, ws)
+        .await
+        .expect("able to query links");
+    assert_eq!(outgoing, parsed.links);
+    assert_eq!(outgoing[0].anchor.as_deref(), Some("method"));
+
+    let incoming = Link::incoming(&target.id, ws)
+        .await
+        .expect("able to query links");
+    assert_eq!(incoming.len(), 1);
+    assert_eq!(incoming[0].source, source.id);
+    assert_eq!(incoming[0].source_title, "Source");
+
+    // re-parsing must not duplicate the stored links
+    Zettel::from_path(&source.path, ws)
+        .await
+        .expect("able to parse zettel");
+    let outgoing = Link::outgoing(&source.id, ws)
+        .await
+        .expect("able to query links");
+    assert_eq!(outgoing.len(), 1);
+}
+
+#[tokio::test]
+async fn anchors_resolve_against_headings() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let target = ZettelBuilder::new(ws)
+        .with_title("Target")
+        .with_content("# Method\n\nhow we do it\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let source = ZettelBuilder::new(ws)
+        .with_title("Source")
+        .with_content(format!(
+            "[x]({0}.md#method)\n\n[[Target#Method]]\n\n[y]({0}.md#missing)\n\n[z](#own-heading)\n\n## Own heading\n",
+            target.id
+        ))
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let parsed = Zettel::from_path(&source.path, ws)
+        .await
+        .expect("able to parse zettel");
+
+    let anchors = parsed
+        .links
+        .iter()
+        .map(|link| (link.dest.clone(), link.anchor.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        anchors,
+        [
+            (target.id.clone(), Some("method")),
+            (target.id.clone(), Some("Method")),
+            (target.id.clone(), Some("missing")),
+            (source.id.clone(), Some("own-heading")),
+        ]
+    );
+
+    let target_zettel = Zettel::from_path(&target.path, ws)
+        .await
+        .expect("able to parse zettel");
+    let heading = parsed.links[0]
+        .heading(&target_zettel)
+        .expect("anchor points at a heading");
+    assert_eq!(heading.text, "Method");
+
+    assert_eq!(parsed.dangling_links.len(), 1);
+    assert_eq!(
+        parsed.dangling_links[0].reason,
+        DanglingReason::UnknownAnchor("missing".to_owned())
+    );
+}
+
+#[tokio::test]
+async fn relations_are_parsed_and_filterable() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let target = ZettelBuilder::new(ws)
+        .with_title("Claim")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let source = ZettelBuilder::new(ws)
+        .with_title("Evidence")
+        .with_content(format!(
+            "[[supports::Claim]]\n\n[x]({0}.md \"rel: Example of\")\n\n[[builds on::{0}|label]]\n\n[[{0}]]\n\n[y]({0}.md \"Some tooltip\")\n",
+            target.id
+        ))
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    let supports = kasten
+        .links_with_relation(&Relation::Supports)
+        .collect::<Vec<_>>();
+    assert_eq!(supports.len(), 1);
+    assert_eq!(supports[0].source, source.id);
+    assert_eq!(supports[0].dest, target.id);
+
+    let relations = Link::outgoing(&source.id, ws)
+        .await
+        .expect("able to query links")
+        .into_iter()
+        .map(|link| link.relation)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        relations,
+        [
+            Some(Relation::Supports),
+            Some(Relation::ExampleOf),
+            Some(Relation::Other("builds-on".to_owned())),
+            None,
+            // plain titles are tooltips, not relations
+            None,
+        ]
+    );
+}
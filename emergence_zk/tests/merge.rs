@@ -0,0 +1,118 @@
+mod common;
+
+use emergence_zk::{FrontMatter, Kasten, ZettelBuilder, ZkError};
+
+use common::write_tags;
+
+#[tokio::test]
+async fn merging_redirects_links_and_unions_tags() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let keep = ZettelBuilder::new(ws)
+        .with_title("Keep")
+        .with_content("# Kept\nkept content\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let absorb = ZettelBuilder::new(ws)
+        .with_title("Absorb")
+        .with_directory("old")
+        .with_content("# Absorbed\nabsorbed content, see [here](#absorbed) and [[Keep]]\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    write_tags(&absorb.path, "shared extra");
+    write_tags(&keep.path, "shared");
+
+    let source = ZettelBuilder::new(ws)
+        .with_title("Source")
+        .with_directory("notes")
+        .with_content(format!(
+            "[[Absorb]] [[supports::{0}#absorbed]] [Absorb](../old/{0}.md) [other](/old/{0})\n",
+            absorb.id
+        ))
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let mut kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    let touched = kasten
+        .merge_zettels(&keep.id, &absorb.id)
+        .await
+        .expect("able to merge");
+
+    let mut expected = vec![keep.path.clone(), source.path.clone()];
+    expected.sort();
+    assert_eq!(touched, expected);
+
+    let (front_matter, content) = FrontMatter::extract_from_file(&keep.path).expect("parses");
+    assert_eq!(front_matter.tag_strings, vec!["shared", "extra"]);
+    assert!(content.contains("kept content"), "{content}");
+    assert!(
+        content.contains("absorbed content, see [here](#absorbed) and [[Keep]]"),
+        "{content}"
+    );
+
+    let (_, content) = FrontMatter::extract_from_file(&source.path).expect("parses");
+    assert!(
+        content.contains(&format!(
+            "[[Keep]] [[supports::{0}#absorbed]] [Keep](../{0}.md) [other](/{0})",
+            keep.id
+        )),
+        "{content}"
+    );
+
+    assert!(!absorb.path.exists());
+    assert!(kasten.zettel(&absorb.id).is_none());
+    assert_eq!(kasten.dangling_links().count(), 0);
+
+    let source = kasten.zettel(&source.id).expect("in graph");
+    assert_eq!(source.links.len(), 4);
+    assert!(source.links.iter().all(|link| link.dest == keep.id));
+
+    // the anchor into the absorbed content still resolves
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    assert_eq!(kasten.dangling_links().count(), 0);
+    assert_eq!(kasten.backlinks(&keep.id).len(), 6);
+}
+
+#[tokio::test]
+async fn merging_into_itself_is_refused() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    let zettel = ZettelBuilder::new(&kasten.ws)
+        .with_title("Only")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let mut kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    let err = kasten
+        .merge_zettels(&zettel.id, &zettel.id)
+        .await
+        .expect_err("can't merge into itself");
+    assert!(matches!(err, ZkError::InvalidOperation(_)), "{err:?}");
+
+    let err = kasten
+        .merge_zettels(&zettel.id, &"missing".into())
+        .await
+        .expect_err("zettel doesn't exist");
+    assert!(matches!(err, ZkError::ZettelNotFound(_)), "{err:?}");
+}
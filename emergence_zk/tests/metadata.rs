@@ -0,0 +1,68 @@
+use std::fs;
+
+use emergence_zk::{Kasten, KastenMetadata, SCHEMA_VERSION, ZkError};
+
+#[tokio::test]
+async fn kasten_identity_survives_reparsing() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let created = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    let first = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    let second = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    assert_eq!(first.id, created.id);
+    assert_eq!(second.id, created.id);
+    assert_eq!(second.name, created.name);
+    assert_eq!(second.created_at, created.created_at);
+
+    // creating it again doesn't give it a new identity
+    let recreated = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    assert_eq!(recreated.id, created.id);
+}
+
+#[tokio::test]
+async fn layouts_are_migrated_or_refused() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let created = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    // kastens from before the metadata existed get it written on parse
+    fs::remove_file(KastenMetadata::path(dir.path())).expect("able to remove metadata");
+    let migrated = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    assert_ne!(migrated.id, created.id);
+    assert_eq!(migrated.name, created.name);
+
+    let metadata = KastenMetadata::load(dir.path()).expect("metadata was written");
+    assert_eq!(metadata.id, migrated.id);
+    assert_eq!(metadata.schema_version, SCHEMA_VERSION);
+
+    // newer layouts are refused
+    KastenMetadata {
+        schema_version: SCHEMA_VERSION + 1,
+        ..metadata
+    }
+    .store(dir.path())
+    .expect("able to store metadata");
+    let err = Kasten::parse(dir.path())
+        .await
+        .expect_err("layout is too new");
+    assert!(matches!(err, ZkError::LayoutError(_)), "{err:?}");
+
+    // folders without .emergence aren't kastens
+    let not_a_kasten = tempfile::tempdir().expect("able to create temp dir");
+    let err = Kasten::parse(not_a_kasten.path())
+        .await
+        .expect_err("not a kasten");
+    assert!(matches!(err, ZkError::LayoutError(_)), "{err:?}");
+}
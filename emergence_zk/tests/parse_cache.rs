@@ -0,0 +1,187 @@
+mod common;
+
+use emergence_zk::{
+    DanglingReason, Kasten, KastenConfig, Workspace, ZettelBuilder, ZettelId, entities::prelude::*,
+};
+
+use common::zettel;
+
+#[tokio::test]
+async fn unchanged_zettels_are_reused_from_the_cache() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let target = ZettelBuilder::new(ws)
+        .with_title("Target")
+        .with_content("# Heading\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let mut source = ZettelBuilder::new(ws)
+        .with_title("Source")
+        .with_content("[[Target#heading]] [[Nobody]] <https://example.com>\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let first = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    let second = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    let (before, after) = (zettel(&first, &source.id), zettel(&second, &source.id));
+    assert_eq!(before, after);
+    assert_eq!(after.links.len(), 1);
+    assert_eq!(after.links[0].dest, target.id);
+    assert_eq!(after.dangling_links.len(), 1);
+    assert_eq!(after.dangling_links[0].reason, DanglingReason::NotFound);
+    assert_eq!(after.external_links.len(), 1);
+    assert_eq!(second.backlinks(&target.id).len(), 1);
+
+    // changed files are picked up
+    source.content = "[[Target]] [[Other]]\n".to_owned();
+    source.flush().expect("able to write zettel");
+
+    let other = ZettelBuilder::new(ws)
+        .with_title("Other")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let third = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    let dests = zettel(&third, &source.id)
+        .links
+        .iter()
+        .map(|link| link.dest.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(dests, vec![target.id.clone(), other.id.clone()]);
+    assert!(zettel(&third, &source.id).dangling_links.is_empty());
+    assert!(zettel(&third, &source.id).external_links.is_empty());
+
+    // removing a zettel re-resolves the unchanged ones that linked to it
+    std::fs::remove_file(&other.path).expect("able to remove zettel");
+
+    let fourth = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    let hits = fourth.search("Other").await.expect("able to search");
+    assert!(hits.iter().all(|hit| hit.id != other.id));
+
+    let source = zettel(&fourth, &source.id);
+    assert_eq!(source.links.len(), 1);
+    assert_eq!(source.dangling_links.len(), 1);
+    assert_eq!(source.dangling_links[0].target, "Other");
+}
+
+#[tokio::test]
+async fn retitled_zettels_re_resolve_wikilinks() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let mut target = ZettelBuilder::new(ws)
+        .with_title("Old Title")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let source = ZettelBuilder::new(ws)
+        .with_title("Source")
+        .with_content("[[Old Title]]\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let first = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    assert_eq!(zettel(&first, &source.id).links.len(), 1);
+
+    target.front_matter.title = "New Title".to_owned();
+    target.flush().expect("able to write zettel");
+
+    let second = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    let source = zettel(&second, &source.id);
+    assert!(source.links.is_empty());
+    assert_eq!(source.dangling_links.len(), 1);
+}
+
+/// Returns the content hash and the settings hash the db has for the zettel with the given `id`.
+async fn stored_stamp(ws: &Workspace, id: &ZettelId) -> (String, Option<String>) {
+    let zettel = ZettelEntity::find_by_nanoid(id.as_str())
+        .one(ws.db.as_ref())
+        .await
+        .expect("able to query")
+        .expect("zettel is in the db");
+    (
+        zettel.content_hash.expect("zettel was parsed"),
+        zettel.settings_hash,
+    )
+}
+
+#[tokio::test]
+async fn changing_the_config_invalidates_the_cache() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    let zettel = ZettelBuilder::new(&kasten.ws)
+        .with_title("Stamped")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let first = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    let (content_hash, before) = stored_stamp(&first.ws, &zettel.id).await;
+    // the content hash is the plain sha256 of the file
+    assert_eq!(content_hash.len(), 64);
+    assert!(before.is_some());
+
+    // settings that don't change how zettels are parsed keep the cache
+    let mut config = KastenConfig::load(dir.path()).expect("able to load config");
+    config.editor = Some("nano".to_owned());
+    config.store(dir.path()).expect("able to store config");
+    let second = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    assert_eq!(
+        stored_stamp(&second.ws, &zettel.id).await,
+        (content_hash.clone(), before.clone())
+    );
+
+    config.date_format = "%d.%m.%Y %H:%M".to_owned();
+    config.store(dir.path()).expect("able to store config");
+    let third = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    let (unchanged, after) = stored_stamp(&third.ws, &zettel.id).await;
+    assert_eq!(unchanged, content_hash);
+    assert!(after.is_some());
+    assert_ne!(after, before);
+
+    config.ignored_paths = vec!["templates".into()];
+    config.store(dir.path()).expect("able to store config");
+    let fourth = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    assert_ne!(stored_stamp(&fourth.ws, &zettel.id).await.1, after);
+}
@@ -0,0 +1,106 @@
+use std::fs;
+
+use emergence_zk::{Kasten, ZettelBuilder, ZkError};
+
+#[tokio::test]
+async fn queries_combine_tags_links_dates_and_text() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let target = ZettelBuilder::new(ws)
+        .with_title("Ownership")
+        .with_content("#rust the borrow checker keeps references valid\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let draft = ZettelBuilder::new(ws)
+        .with_title("Lifetimes")
+        .with_content(format!(
+            "#rust/lifetimes #draft the borrow checker again, see [[{}]]\n",
+            target.id
+        ))
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let done = ZettelBuilder::new(ws)
+        .with_title("Borrowing")
+        .with_content(format!(
+            "#rust the borrow checker, see [[{}]] and [[Lifetimes]]\n",
+            target.id
+        ))
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let old = ZettelBuilder::new(ws)
+        .with_title("Gardening")
+        .with_content("tomatoes need a lot of sun\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+    let text = fs::read_to_string(&old.path)
+        .expect("able to read zettel")
+        .lines()
+        .map(|line| {
+            if line.starts_with("Date:") {
+                "Date: 2020-01-01 12:00:00 AM".to_owned()
+            } else {
+                line.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    fs::write(&old.path, text).expect("able to write zettel");
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    let query = async |query: &str| kasten.query(query).await.expect("able to query");
+    let sorted = |mut ids: Vec<_>| {
+        ids.sort();
+        ids
+    };
+
+    assert_eq!(
+        query(&format!(
+            "tag:rust AND -tag:draft AND links-to:{} AND created>2025-01-01 AND \"borrow checker\"",
+            target.id
+        ))
+        .await,
+        vec![done.id.clone()]
+    );
+    assert_eq!(
+        query("tag:rust").await,
+        sorted(vec![target.id.clone(), draft.id.clone(), done.id.clone()])
+    );
+    assert_eq!(
+        query("links-to:ownership").await,
+        sorted(vec![draft.id.clone(), done.id.clone()])
+    );
+    assert_eq!(
+        query("linked-from:Borrowing -title:own").await,
+        vec![draft.id.clone()]
+    );
+    assert_eq!(
+        query("has:backlinks").await,
+        sorted(vec![target.id.clone(), draft.id.clone()])
+    );
+    assert_eq!(query("created<2025-01-01").await, vec![old.id.clone()]);
+    assert_eq!(
+        query("(tag:draft OR tomatoes) NOT title:garden").await,
+        vec![draft.id.clone()]
+    );
+    assert_eq!(query("NOT borrow").await, vec![old.id.clone()]);
+    assert!(query("tag:nothing").await.is_empty());
+
+    assert!(matches!(
+        kasten.query("tag:rust AND").await,
+        Err(ZkError::ParseError(_))
+    ));
+}
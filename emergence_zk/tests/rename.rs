@@ -0,0 +1,149 @@
+use emergence_zk::{FrontMatter, Kasten, ZettelBuilder, ZkError};
+
+#[tokio::test]
+async fn renaming_rewrites_links_by_title() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let target = ZettelBuilder::new(ws)
+        .with_title("Old Title")
+        .with_content("# Goals\nsee [[Old Title#goals]]\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let source = ZettelBuilder::new(ws)
+        .with_title("Source")
+        .with_directory("sub")
+        .with_content(format!(
+            "[[Old Title]] and [[supports::Old Title#goals]]\n\n[Old Title](../{0}.md) [[{0}|Old Title]] [[{0}]] [custom](../{0})\n",
+            target.id
+        ))
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let unrelated = ZettelBuilder::new(ws)
+        .with_title("Unrelated")
+        .with_content("[[New Title]] and Old Title\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let mut kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    assert_eq!(kasten.dangling_links().count(), 1);
+
+    let touched = kasten
+        .rename_zettel(&target.id, "New Title")
+        .await
+        .expect("able to rename");
+
+    let mut expected = vec![target.path.clone(), source.path.clone()];
+    expected.sort();
+    assert_eq!(touched, expected);
+
+    let (front_matter, content) =
+        FrontMatter::extract_from_file(&target.path).expect("still parses");
+    assert_eq!(front_matter.title, "New Title");
+    assert!(content.contains("see [[New Title#goals]]"), "{content}");
+
+    let (_, content) = FrontMatter::extract_from_file(&source.path).expect("still parses");
+    assert!(
+        content.contains("[[New Title]] and [[supports::New Title#goals]]"),
+        "{content}"
+    );
+    assert!(
+        content.contains(&format!(
+            "[New Title](../{0}.md) [[{0}|New Title]] [[{0}]] [custom](../{0})",
+            target.id
+        )),
+        "{content}"
+    );
+
+    // the unrelated zettel isn't written, but its link resolves now
+    let (_, content) = FrontMatter::extract_from_file(&unrelated.path).expect("still parses");
+    assert!(content.contains("[[New Title]] and Old Title"));
+    assert_eq!(kasten.dangling_links().count(), 0);
+
+    let source = kasten.zettel(&source.id).expect("still in the graph");
+    assert_eq!(source.links.len(), 6);
+    assert!(source.links.iter().all(|link| link.dest == target.id));
+    assert_eq!(kasten.backlinks(&target.id).len(), 8);
+
+    // a fresh parse agrees with the kasten
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    assert_eq!(kasten.dangling_links().count(), 0);
+    assert_eq!(
+        kasten
+            .zettel(&target.id)
+            .expect("in graph")
+            .front_matter
+            .title,
+        "New Title"
+    );
+}
+
+#[tokio::test]
+async fn invalid_renames_are_refused() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    let zettel = ZettelBuilder::new(&kasten.ws)
+        .with_title("Title")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let mut kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    for title in ["  ", "with [[brackets]]", "a|b"] {
+        let err = kasten
+            .rename_zettel(&zettel.id, title)
+            .await
+            .expect_err("title is invalid");
+        assert!(matches!(err, ZkError::InvalidOperation(_)), "{err:?}");
+    }
+
+    let err = kasten
+        .rename_zettel(&"missing".into(), "Title")
+        .await
+        .expect_err("zettel doesn't exist");
+    assert!(matches!(err, ZkError::ZettelNotFound(_)), "{err:?}");
+}
+
+#[tokio::test]
+async fn renaming_keeps_hand_written_yaml() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    let path = dir.path().join("handwritn.md");
+    let header = "---\n# kept as is\ndate: 2024-03-01\nstatus: \"draft\"\ntitle: 'Before'\ntags: [a, b]\n---\n";
+    std::fs::write(&path, format!("{header}content")).expect("able to write file");
+
+    let mut kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    kasten
+        .rename_zettel(&"handwritn".into(), "After")
+        .await
+        .expect("able to rename");
+
+    let text = std::fs::read_to_string(&path).expect("able to read zettel");
+    assert_eq!(
+        text,
+        header.replace("title: 'Before'", "title: After") + "content"
+    );
+}
@@ -0,0 +1,100 @@
+use emergence_zk::{Kasten, ZettelBuilder};
+
+#[tokio::test]
+async fn search_ranks_and_snippets() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let borrow = ZettelBuilder::new(ws)
+        .with_title("Ownership")
+        .with_content("the borrow checker keeps references valid\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    ZettelBuilder::new(ws)
+        .with_title("Gardening")
+        .with_content("tomatoes need a lot of sun\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    let hits = kasten
+        .search("borrow checker")
+        .await
+        .expect("able to search");
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].id, borrow.id);
+    assert_eq!(hits[0].title, "Ownership");
+    assert!(hits[0].snippet.contains("**borrow**"));
+
+    // quotes and operators are treated as plain words
+    let hits = kasten.search("\"sun\" OR -").await.expect("able to search");
+    assert!(hits.is_empty());
+
+    // re-parsing must not duplicate entries
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    let hits = kasten.search("tomatoes").await.expect("able to search");
+    assert_eq!(hits.len(), 1);
+}
+
+#[tokio::test]
+async fn zettels_that_stop_parsing_are_forgotten() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let broken = ZettelBuilder::new(ws)
+        .with_title("Seabirds")
+        .with_content("#birds the albatross glides for hours\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let source = ZettelBuilder::new(ws)
+        .with_title("Source")
+        .with_content("see [[Seabirds]]\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    let hits = kasten.search("albatross").await.expect("able to search");
+    assert_eq!(hits.len(), 1);
+
+    // without its front matter the zettel can't be parsed anymore
+    std::fs::write(&broken.path, "the albatross glides for hours\n").expect("able to write");
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    assert!(kasten.zettel(&broken.id).is_none());
+    assert!(kasten.diagnostics.iter().any(|d| d.path == broken.path));
+
+    let hits = kasten.search("albatross").await.expect("able to search");
+    assert!(hits.is_empty());
+    assert!(
+        kasten
+            .tagged("birds")
+            .await
+            .expect("able to query")
+            .is_empty()
+    );
+
+    let source = kasten.zettel(&source.id).expect("in graph");
+    assert!(source.links.is_empty());
+    assert_eq!(source.dangling_links.len(), 1);
+}
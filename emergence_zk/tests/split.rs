@@ -0,0 +1,129 @@
+mod common;
+
+use emergence_zk::{FrontMatter, Kasten, Split, ZettelBuilder, ZkError};
+
+use common::write_tags;
+
+#[tokio::test]
+async fn splitting_creates_linked_pieces() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let long = ZettelBuilder::new(ws)
+        .with_title("Long")
+        .with_directory("notes")
+        .with_content(
+            "intro, see [below](#second)\n\n# First\nfirst text\n\n## Detail\ndetail text\n\n# Second\nsecond text, back to [first](#first)\n\nplain line\nanother one",
+        )
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    write_tags(&long.path, "big");
+
+    let source = ZettelBuilder::new(ws)
+        .with_title("Source")
+        .with_content(format!(
+            "[[Long#detail]] [x](notes/{0}.md#second) [[{0}]]\n",
+            long.id
+        ))
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let mut kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    // file line 6 is the blank line after the front matter
+    let pieces = kasten
+        .split_zettel(
+            &long.id,
+            &[Split::Lines(18..=18), Split::Heading("first".to_owned())],
+        )
+        .await
+        .expect("able to split");
+    assert_eq!(pieces.len(), 2);
+
+    let first = kasten.zettel(&pieces[0]).expect("in graph").clone();
+    let line = kasten.zettel(&pieces[1]).expect("in graph").clone();
+    assert_eq!(first.front_matter.title, "First");
+    assert_eq!(line.front_matter.title, "Long (2)");
+    assert_eq!(first.front_matter.tag_strings, vec!["big"]);
+    assert_eq!(first.path.parent(), long.path.parent());
+    assert!(first.content.contains("## Detail\ndetail text"));
+    assert_eq!(line.content.trim(), "plain line");
+
+    let (_, content) = FrontMatter::extract_from_file(&long.path).expect("parses");
+    assert!(
+        content.contains(&format!("[First]({}.md)", first.id)),
+        "{content}"
+    );
+    assert!(
+        content.contains(&format!("[Long (2)]({}.md)", line.id)),
+        "{content}"
+    );
+    assert!(!content.contains("first text"), "{content}");
+    assert!(
+        content.contains(&format!("back to [first]({}.md#first)", first.id)),
+        "{content}"
+    );
+    assert!(content.contains("see [below](#second)"), "{content}");
+
+    let (_, content) = FrontMatter::extract_from_file(&source.path).expect("parses");
+    assert!(
+        content.contains(&format!(
+            "[[{0}#detail]] [x](notes/{1}.md#second) [[{1}]]",
+            first.id, long.id
+        )),
+        "{content}"
+    );
+
+    assert_eq!(kasten.dangling_links().count(), 0);
+    let original = kasten.zettel(&long.id).expect("in graph");
+    assert!(original.links.iter().any(|link| link.dest == first.id));
+    assert!(original.links.iter().any(|link| link.dest == line.id));
+    assert_eq!(kasten.backlinks(&first.id).len(), 3);
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    assert_eq!(kasten.dangling_links().count(), 0);
+}
+
+#[tokio::test]
+async fn invalid_splits_are_refused() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    let zettel = ZettelBuilder::new(&kasten.ws)
+        .with_title("Short")
+        .with_content("# Only\ntext\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let mut kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    for splits in [
+        vec![Split::Heading("missing".to_owned())],
+        vec![Split::Lines(7..=40)],
+        vec![Split::Lines(0..=1)],
+        // the front matter isn't part of the content
+        vec![Split::Lines(3..=7)],
+        vec![Split::Heading("only".to_owned()), Split::Lines(8..=8)],
+    ] {
+        let err = kasten
+            .split_zettel(&zettel.id, &splits)
+            .await
+            .expect_err("split is invalid");
+        assert!(matches!(err, ZkError::InvalidOperation(_)), "{err:?}");
+    }
+}
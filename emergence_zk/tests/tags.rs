@@ -0,0 +1,470 @@
+mod common;
+
+use std::{collections::BTreeMap, fs};
+
+use common::write_tags;
+use emergence_zk::{
+    Color, FrontMatter, Kasten, KastenConfig, Tag, TagRegistry, ZettelBuilder, ZkError,
+    entities::{prelude::*, zettel_tag},
+};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+#[tokio::test]
+async fn parent_tags_include_their_descendants() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let mut ids = vec![];
+    for (title, tags) in [
+        ("Lsp", "Project/Emergence/LSP"),
+        ("Gui", "project/emergence/gui project/emergence/lsp"),
+        ("Project", "project"),
+        ("Elsewhere", "projects"),
+    ] {
+        let zettel = ZettelBuilder::new(ws)
+            .with_title(title)
+            .build()
+            .await
+            .expect("able to build zettel");
+        write_tags(&zettel.path, tags);
+        ids.push(zettel.id);
+    }
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    let lsp = kasten.zettel(&ids[0]).expect("in graph");
+    assert_eq!(lsp.tags[0].name, "project/emergence/lsp");
+    assert_eq!(lsp.tags[0].parent(), Some("project/emergence"));
+    assert_eq!(lsp.tags[0].leaf(), "lsp");
+
+    let mut expected = ids[..3].to_vec();
+    expected.sort();
+    assert_eq!(
+        kasten.tagged("project").await.expect("able to query"),
+        expected
+    );
+
+    let mut expected = ids[..2].to_vec();
+    expected.sort();
+    assert_eq!(
+        kasten
+            .tagged("project/emergence/")
+            .await
+            .expect("able to query"),
+        expected
+    );
+    assert_eq!(
+        kasten
+            .tagged("project/emergence/gui")
+            .await
+            .expect("able to query"),
+        vec![ids[1].clone()]
+    );
+
+    let tree = kasten.tag_tree().await.expect("able to build tree");
+    assert_eq!(tree.len(), 2);
+    let project = &tree[0];
+    assert_eq!(
+        (project.name.as_str(), project.count, project.total),
+        ("project", 1, 3)
+    );
+    let emergence = &project.children[0];
+    assert_eq!(
+        (emergence.leaf(), emergence.count, emergence.total),
+        ("emergence", 0, 2)
+    );
+    let counts = emergence
+        .children
+        .iter()
+        .map(|node| (node.leaf(), node.count, node.total))
+        .collect::<Vec<_>>();
+    assert_eq!(counts, vec![("gui", 1, 1), ("lsp", 2, 2)]);
+    assert_eq!((tree[1].name.as_str(), tree[1].total), ("projects", 1));
+}
+
+#[tokio::test]
+async fn creating_a_tag_creates_its_ancestors() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    let tag = Tag::get_or_new("Area//Rust/", &kasten.ws)
+        .await
+        .expect("able to create tag");
+    assert_eq!(tag.name, "area/rust");
+
+    let tree = kasten.tag_tree().await.expect("able to build tree");
+    assert_eq!(tree.len(), 1);
+    assert_eq!(tree[0].name, "area");
+    assert!(tree[0].color.is_some());
+    assert_eq!(tree[0].children[0].name, "area/rust");
+    assert_eq!(tree[0].total, 0);
+
+    let again = Tag::get_or_new("area/rust", &kasten.ws)
+        .await
+        .expect("able to get tag");
+    assert_eq!(again, tag);
+
+    // creating a tag that exists already only changes its color
+    let red = Color::rgb(0xff, 0, 0);
+    let recolored = Tag::new("Area/Rust", red, &kasten.ws)
+        .await
+        .expect("able to create existing tag");
+    assert_eq!(recolored.name, "area/rust");
+    assert_eq!(recolored.color, red);
+    assert_eq!(
+        TagRegistry::new(&kasten.ws)
+            .all()
+            .await
+            .expect("able to list tags")
+            .len(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn unused_tags_are_removed() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let mut zettels = vec![];
+    for title in ["One", "Two"] {
+        let zettel = ZettelBuilder::new(ws)
+            .with_title(title)
+            .build()
+            .await
+            .expect("able to build zettel");
+        write_tags(&zettel.path, "rust lang/rust");
+        zettels.push(zettel);
+    }
+
+    // both zettels share the same rows
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    assert!(kasten.diagnostics.is_empty(), "{:?}", kasten.diagnostics);
+    let names = |tags: Vec<Tag>| tags.into_iter().map(|tag| tag.name).collect::<Vec<_>>();
+    let registry = TagRegistry::new(&kasten.ws);
+    assert_eq!(
+        names(registry.all().await.expect("able to list")),
+        vec!["lang", "lang/rust", "rust"]
+    );
+
+    for zettel in &zettels {
+        let text = fs::read_to_string(&zettel.path)
+            .expect("able to read zettel")
+            .replace("rust lang/rust", "lang/go");
+        fs::write(&zettel.path, text).expect("able to write zettel");
+    }
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    let registry = TagRegistry::new(&kasten.ws);
+    assert_eq!(
+        names(registry.all().await.expect("able to list")),
+        vec!["lang", "lang/go"]
+    );
+
+    // a tag nobody uses yet goes away with the next cleanup
+    registry.resolve("unused").await.expect("able to create");
+    assert_eq!(
+        registry.remove_orphans().await.expect("able to clean up"),
+        vec!["unused"]
+    );
+}
+
+#[tokio::test]
+async fn renaming_a_tag_rewrites_front_matter() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let mut zettels = vec![];
+    for (title, tags) in [
+        ("Nested", "project/lsp other"),
+        ("Both", "project work"),
+        ("Untouched", "other"),
+    ] {
+        let zettel = ZettelBuilder::new(ws)
+            .with_title(title)
+            .build()
+            .await
+            .expect("able to build zettel");
+        write_tags(&zettel.path, tags);
+        zettels.push(zettel);
+    }
+
+    let mut kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    let color = TagRegistry::new(&kasten.ws)
+        .get("project/lsp")
+        .await
+        .expect("able to get")
+        .expect("exists")
+        .color;
+
+    let touched = kasten
+        .rename_tag("Project", "work")
+        .await
+        .expect("able to rename");
+    let mut expected = vec![zettels[0].path.clone(), zettels[1].path.clone()];
+    expected.sort();
+    assert_eq!(touched, expected);
+
+    let tags = |idx: usize| {
+        FrontMatter::extract_from_file(&zettels[idx].path)
+            .expect("parses")
+            .0
+            .tag_strings
+    };
+    assert_eq!(tags(0), vec!["work/lsp", "other"]);
+    assert_eq!(tags(1), vec!["work"]);
+    assert_eq!(tags(2), vec!["other"]);
+
+    let registry = TagRegistry::new(&kasten.ws);
+    assert!(
+        registry
+            .get("project")
+            .await
+            .expect("able to get")
+            .is_none()
+    );
+    let renamed = registry
+        .get("work/lsp")
+        .await
+        .expect("able to get")
+        .expect("exists");
+    assert_eq!(renamed.color, color);
+
+    let nested = kasten.zettel(&zettels[0].id).expect("in graph");
+    assert!(nested.tags.iter().any(|tag| tag.name == "work/lsp"));
+    assert_eq!(kasten.tagged("work").await.expect("able to query").len(), 2);
+
+    for (old, new) in [("missing", "x"), ("work", "work/sub"), ("work", "/")] {
+        let err = kasten
+            .rename_tag(old, new)
+            .await
+            .expect_err("rename is invalid");
+        assert!(matches!(err, ZkError::InvalidOperation(_)), "{err:?}");
+    }
+}
+
+#[tokio::test]
+async fn inline_tags_are_merged_into_the_tag_set() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    let zettel = ZettelBuilder::new(&kasten.ws)
+        .with_title("Inline")
+        .with_content("#todo check this #Question\n\n```\n#code\n```\n\n# Heading #not\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+    write_tags(&zettel.path, "todo");
+
+    let mut kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+
+    let parsed = kasten.zettel(&zettel.id).expect("in graph");
+    let mut names = parsed
+        .tags
+        .iter()
+        .map(|tag| tag.name.as_str())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, vec!["question", "todo"]);
+
+    let inline = parsed.inline_tags();
+    assert_eq!(inline.len(), 2);
+    assert_eq!(inline[1].name, "question");
+    // after the five lines of front matter and the blank one following them
+    assert_eq!(inline[1].line, 7);
+
+    let links = ZettelTag::find()
+        .filter(zettel_tag::Column::ZettelNanoId.eq(zettel.id.as_str()))
+        .find_also_related(TagEntity)
+        .all(kasten.ws.db.as_ref())
+        .await
+        .expect("able to query")
+        .into_iter()
+        .map(|(link, tag)| (tag.expect("tag exists").name, link.inline))
+        .collect::<BTreeMap<_, _>>();
+    assert!(!links["todo"]);
+    assert!(links["question"]);
+    assert_eq!(
+        kasten.tagged("question").await.expect("able to query"),
+        vec![zettel.id.clone()]
+    );
+
+    kasten
+        .rename_tag("question", "open/question")
+        .await
+        .expect("able to rename");
+    let (front_matter, content) = FrontMatter::extract_from_file(&zettel.path).expect("parses");
+    assert_eq!(front_matter.tag_strings, vec!["todo"]);
+    assert!(
+        content.contains("#todo check this #open/question\n"),
+        "{content}"
+    );
+
+    // dropping the inline tag drops it from the zettel
+    fs::write(
+        &zettel.path,
+        fs::read_to_string(&zettel.path)
+            .expect("able to read zettel")
+            .replace(" #open/question", ""),
+    )
+    .expect("able to write zettel");
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    assert!(
+        TagRegistry::new(&kasten.ws)
+            .get("open/question")
+            .await
+            .expect("able to get")
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn unicode_names_are_normalized() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    let mut ids = vec![];
+    // precomposed and decomposed umlauts, in any case
+    for (title, tags) in [
+        ("Precomposed", "\u{c4}rger 日本語/メモ"),
+        ("Decomposed", "a\u{308}rger c++"),
+    ] {
+        let zettel = ZettelBuilder::new(&kasten.ws)
+            .with_title(title)
+            .build()
+            .await
+            .expect("able to build zettel");
+        write_tags(&zettel.path, tags);
+        ids.push(zettel.id);
+    }
+    ids.sort();
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    assert_eq!(kasten.tagged("ÄRGER").await.expect("able to query"), ids);
+    assert_eq!(
+        kasten.tagged("日本語").await.expect("able to query").len(),
+        1
+    );
+
+    let names = TagRegistry::new(&kasten.ws)
+        .all()
+        .await
+        .expect("able to list")
+        .into_iter()
+        .map(|tag| tag.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["\u{e4}rger", "日本語", "日本語/メモ"]);
+
+    // the invalid tag is left out and reported
+    assert_eq!(kasten.diagnostics.len(), 1, "{:?}", kasten.diagnostics);
+    assert!(kasten.diagnostics[0].message.contains("c++"));
+    assert_eq!(kasten.diagnostics[0].line, 4);
+
+    let tag = Tag::get_or_new("A\u{308}RGER", &kasten.ws)
+        .await
+        .expect("able to get tag");
+    assert_eq!(tag.name, "\u{e4}rger");
+    let err = Tag::get_or_new("no spaces", &kasten.ws)
+        .await
+        .expect_err("name is invalid");
+    assert!(matches!(err, ZkError::ParseError(_)), "{err:?}");
+}
+
+#[tokio::test]
+async fn tags_get_colors_from_the_palette() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    let zettel = ZettelBuilder::new(&kasten.ws)
+        .with_title("Colored")
+        .build()
+        .await
+        .expect("able to build zettel");
+    write_tags(&zettel.path, "first second third");
+    // zettels are parsed side by side, their new tags still get colors of their own
+    let other = ZettelBuilder::new(&kasten.ws)
+        .with_title("Also Colored")
+        .build()
+        .await
+        .expect("able to build zettel");
+    write_tags(&other.path, "other another");
+
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    let registry = TagRegistry::new(&kasten.ws);
+    let tags = registry.all().await.expect("able to list");
+    assert_eq!(tags.len(), 5);
+    for tag in &tags {
+        assert!(Color::PALETTE.contains(&tag.color), "{tag:?}");
+        assert_eq!(tags.iter().filter(|t| t.color == tag.color).count(), 1);
+    }
+
+    let stored = TagEntity::find_by_name("first")
+        .one(kasten.ws.db.as_ref())
+        .await
+        .expect("able to query")
+        .expect("exists");
+    assert_eq!(
+        stored.color.parse::<Color>().ok(),
+        tags.iter()
+            .find(|tag| tag.name == "first")
+            .map(|tag| tag.color)
+    );
+
+    // graph nodes take the color of the first tag
+    let first = registry
+        .set_color("first", "#123456".parse().expect("valid color"))
+        .await
+        .expect("able to set color");
+    assert_eq!(first.color, Color::rgb(0x12, 0x34, 0x56));
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    let gid = kasten.zid_to_gid[&zettel.id];
+    let node = kasten.graph.node(gid).expect("in graph");
+    assert_eq!(node.color(), Some(first.color.into()));
+
+    // a configured color wins over the palette
+    let mut config = KastenConfig::load(dir.path()).expect("able to load config");
+    config.default_tag_color = "rgb(1, 2, 3)".to_owned();
+    config.store(dir.path()).expect("able to store config");
+    let kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    let tag = Tag::get_or_new("fourth", &kasten.ws)
+        .await
+        .expect("able to create tag");
+    assert_eq!(tag.color, Color::rgb(1, 2, 3));
+}
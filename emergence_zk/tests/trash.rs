@@ -0,0 +1,90 @@
+use emergence_zk::{DanglingReason, Kasten, ZettelBuilder, ZkError};
+
+#[tokio::test]
+async fn deleted_zettels_go_to_the_trash_and_come_back() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+    let ws = &kasten.ws;
+
+    let doomed = ZettelBuilder::new(ws)
+        .with_title("Doomed")
+        .with_directory("notes")
+        .with_content("links to [[Source]]\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let source = ZettelBuilder::new(ws)
+        .with_title("Source")
+        .with_content(format!("[[Doomed]] and [[{}]]\n", doomed.id))
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let mut kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    assert_eq!(kasten.backlinks(&doomed.id).len(), 2);
+
+    let broken = kasten
+        .delete_zettel(&doomed.id)
+        .await
+        .expect("able to delete");
+    assert_eq!(broken, vec![source.id.clone()]);
+
+    assert!(!doomed.path.exists());
+    assert!(
+        kasten
+            .trash_dir()
+            .join(format!("notes/{}.md", doomed.id))
+            .exists()
+    );
+    assert!(kasten.zettel(&doomed.id).is_none());
+    assert!(kasten.backlinks(&source.id).is_empty());
+
+    let dangling = kasten.dangling_links().collect::<Vec<_>>();
+    assert_eq!(dangling.len(), 2);
+    assert!(
+        dangling
+            .iter()
+            .all(|link| link.source == source.id && link.reason == DanglingReason::NotFound)
+    );
+    assert!(
+        kasten
+            .search("Doomed")
+            .await
+            .expect("able to search")
+            .iter()
+            .all(|hit| hit.id != doomed.id)
+    );
+
+    // a fresh parse doesn't know about it either
+    let mut kasten = Kasten::parse(dir.path())
+        .await
+        .expect("able to parse kasten");
+    assert!(kasten.zettel(&doomed.id).is_none());
+    assert_eq!(kasten.dangling_links().count(), 2);
+
+    let repaired = kasten
+        .restore_zettel(&doomed.id)
+        .await
+        .expect("able to restore");
+    assert_eq!(repaired, vec![source.id.clone()]);
+
+    assert!(doomed.path.exists());
+    assert_eq!(kasten.dangling_links().count(), 0);
+    assert_eq!(kasten.backlinks(&doomed.id).len(), 2);
+    assert_eq!(kasten.backlinks(&source.id).len(), 1);
+    assert_eq!(
+        kasten.zettel(&doomed.id).expect("in graph").path,
+        doomed.path
+    );
+
+    let err = kasten
+        .restore_zettel(&doomed.id)
+        .await
+        .expect_err("nothing left to restore");
+    assert!(matches!(err, ZkError::ZettelNotFound(_)), "{err:?}");
+}
@@ -0,0 +1,109 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use emergence_zk::{Kasten, KastenHandle, ZettelBuilder, ZettelId, entities::prelude::*};
+
+/// Polls `check` until it holds, giving the watcher time to pick up the change.
+async fn eventually(mut check: impl AsyncFnMut() -> bool) -> bool {
+    for _ in 0..50 {
+        if check().await {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    false
+}
+
+async fn stored_path(kasten: &KastenHandle, id: &ZettelId) -> Option<String> {
+    let ws = kasten.lock().expect("not poisoned").ws.clone();
+    ZettelEntity::find_by_nanoid(id.as_str())
+        .one(ws.db.as_ref())
+        .await
+        .expect("able to query")
+        .and_then(|zettel| zettel.path)
+}
+
+fn graph_path(kasten: &KastenHandle, id: &ZettelId) -> Option<PathBuf> {
+    kasten
+        .lock()
+        .expect("not poisoned")
+        .zettel(id)
+        .map(|zettel| zettel.path.clone())
+}
+
+#[tokio::test]
+async fn watching_keeps_renamed_zettels() {
+    let dir = tempfile::tempdir().expect("able to create temp dir");
+    let kasten = Kasten::new(dir.path())
+        .await
+        .expect("able to create kasten");
+
+    let zettel = ZettelBuilder::new(&kasten.ws)
+        .with_title("Moving")
+        .with_content("#wandering\n")
+        .build()
+        .await
+        .expect("able to build zettel");
+
+    let kasten: KastenHandle = Arc::new(Mutex::new(
+        Kasten::parse(dir.path())
+            .await
+            .expect("able to parse kasten"),
+    ));
+    let watcher = tokio::spawn(Kasten::watch(kasten.clone()));
+    // give the watcher a moment to start
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // moved into a subfolder, the zettel keeps its id
+    fs::create_dir(dir.path().join("moved")).expect("able to create dir");
+    let moved = dir.path().join("moved").join(format!("{}.md", zettel.id));
+    fs::rename(&zettel.path, &moved).expect("able to move zettel");
+
+    let expected = format!("moved/{}.md", zettel.id);
+    assert!(
+        eventually(async || {
+            stored_path(&kasten, &zettel.id).await.as_ref() == Some(&expected)
+                && graph_path(&kasten, &zettel.id).as_ref() == Some(&moved)
+        })
+        .await,
+        "the zettel has to be at its new path in the db and the graph"
+    );
+
+    // give any events still in flight for the old path time to arrive
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(stored_path(&kasten, &zettel.id).await, Some(expected));
+    let ws = kasten.lock().expect("not poisoned").ws.clone();
+    assert_eq!(
+        kasten
+            .lock()
+            .expect("not poisoned")
+            .zettel(&zettel.id)
+            .map(|zettel| zettel.tags.len()),
+        Some(1)
+    );
+    let tag = TagEntity::find_by_name("wandering")
+        .one(ws.db.as_ref())
+        .await
+        .expect("able to query");
+    assert!(tag.is_some());
+
+    // moving it out of the kasten removes it
+    let trash = dir.path().join(".trash");
+    fs::create_dir(&trash).expect("able to create dir");
+    fs::rename(&moved, trash.join(format!("{}.md", zettel.id))).expect("able to move zettel");
+
+    assert!(
+        eventually(async || {
+            stored_path(&kasten, &zettel.id).await.is_none()
+                && graph_path(&kasten, &zettel.id).is_none()
+        })
+        .await,
+        "the zettel has to be gone from the db and the graph"
+    );
+
+    watcher.abort();
+}